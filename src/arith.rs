@@ -0,0 +1,150 @@
+//! Modular arithmetic helpers shared by the sieve, the primality tests and the
+//! proof backends.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+/// Returns `a^-1 mod n`, or `None` if `gcd(a, n) != 1`.
+pub fn mod_inverse(a: &BigUint, n: &BigUint) -> Option<BigUint> {
+    let a = BigInt::from_biguint(Sign::Plus, a % n);
+    let n = BigInt::from_biguint(Sign::Plus, n.clone());
+    let egcd = a.extended_gcd(&n);
+    if !egcd.gcd.is_one() {
+        return None;
+    }
+    egcd.x.mod_floor(&n).to_biguint()
+}
+
+/// `(a - b) mod n` for `a, b < n`.
+pub fn mod_sub(a: &BigUint, b: &BigUint, n: &BigUint) -> BigUint {
+    if a >= b { a - b } else { n - (b - a) }
+}
+
+/// Reduces a signed integer into `[0, n)`.
+pub fn to_residue(a: &BigInt, n: &BigUint) -> BigUint {
+    let n = BigInt::from_biguint(Sign::Plus, n.clone());
+    a.mod_floor(&n).to_biguint().unwrap()
+}
+
+/// Jacobi symbol `(a / n)` for odd `n`.
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    debug_assert!(n.is_odd());
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while !a.is_zero() {
+        let twos = a.trailing_zeros().unwrap_or(0);
+        a >>= twos;
+        let n_mod_8 = (&n % 8u32).to_u32_digits().first().copied().unwrap_or(0);
+        if twos % 2 == 1 && (n_mod_8 == 3 || n_mod_8 == 5) {
+            result = -result;
+        }
+        if (&a % 4u32) == BigUint::from(3u32) && n_mod_8 % 4 == 3 {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a %= &n;
+    }
+    if n.is_one() { result } else { 0 }
+}
+
+/// Kronecker symbol `(d / n)` for a signed `d` and odd `n`.
+pub fn kronecker(d: i64, n: &BigUint) -> i32 {
+    let residue = to_residue(&BigInt::from(d), n);
+    jacobi(&residue, n)
+}
+
+/// Square root of `a` modulo an odd prime `p` (Tonelli–Shanks).
+///
+/// Returns `None` if `a` is not a quadratic residue, or if the computation
+/// exposes `p` as composite.
+pub fn sqrt_mod(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let a = a % p;
+    if a.is_zero() {
+        return Some(a);
+    }
+    if jacobi(&a, p) != 1 {
+        return None;
+    }
+    let one = BigUint::one();
+    let p_minus_1 = p - &one;
+
+    if (p % 4u32) == BigUint::from(3u32) {
+        let r = a.modpow(&((p + &one) >> 2), p);
+        return ((&r * &r) % p == a).then_some(r);
+    }
+
+    let s = p_minus_1.trailing_zeros().unwrap();
+    let q = &p_minus_1 >> s;
+    let mut z = BigUint::from(2u32);
+    while jacobi(&z, p) != -1 {
+        z += 1u32;
+        if &z >= p {
+            return None;
+        }
+    }
+
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = a.modpow(&q, p);
+    let mut r = a.modpow(&((&q + &one) >> 1), p);
+    while !t.is_one() {
+        let mut i = 0;
+        let mut t2 = t.clone();
+        while !t2.is_one() {
+            t2 = (&t2 * &t2) % p;
+            i += 1;
+            if i == m {
+                return None;
+            }
+        }
+        let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = (&b * &b) % p;
+        t = (&t * &c) % p;
+        r = (&r * &b) % p;
+    }
+    Some(r)
+}
+
+/// Returns `sqrt(n)` if `n` is a perfect square.
+pub fn exact_sqrt(n: &BigUint) -> Option<BigUint> {
+    let r = n.sqrt();
+    (&r * &r == *n).then_some(r)
+}
+
+/// Solves `4n = x^2 + |d| y^2` for a prime `n` and negative discriminant `d`
+/// (modified Cornacchia).
+pub fn cornacchia4(d: i64, n: &BigUint) -> Option<(BigUint, BigUint)> {
+    debug_assert!(d < 0);
+    let abs_d = BigUint::from(d.unsigned_abs());
+    if kronecker(d, n) != 1 {
+        return None;
+    }
+    let mut x0 = sqrt_mod(&to_residue(&BigInt::from(d), n), n)?;
+    let d_odd = d.rem_euclid(2) == 1;
+    if x0.is_odd() != d_odd {
+        x0 = n - x0;
+    }
+
+    let four_n: BigUint = n << 2;
+    let mut a: BigUint = n << 1;
+    let mut b = x0;
+    let limit = four_n.sqrt();
+    while b > limit {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+
+    let b2 = &b * &b;
+    if b2 > four_n {
+        return None;
+    }
+    let (c, rem) = (&four_n - &b2).div_rem(&abs_d);
+    if !rem.is_zero() {
+        return None;
+    }
+    exact_sqrt(&c).map(|y| (b, y))
+}
@@ -0,0 +1,179 @@
+//! Cooperative cancellation of long searches.
+//!
+//! A [`CancellationToken`] is a shared flag: clone it into the generator with
+//! [`PrimeGenerator::cancel_token`](crate::PrimeGenerator::cancel_token) and
+//! call [`cancel`](CancellationToken::cancel) from any thread. Searches check
+//! the token and their deadline before every candidate, and proofs check them
+//! while they run, so a cancelled search returns [`PrimeError::Cancelled`]
+//! (or [`PrimeError::TimedOut`]) within about one probable-prime test.
+//!
+//! ```no_run
+//! use std::time::{Duration, Instant};
+//! use large_primes::{CancellationToken, PrimeError, PrimeGenerator};
+//!
+//! let token = CancellationToken::new();
+//! let mut generator = PrimeGenerator::new()
+//!     .digits(1500)
+//!     .cancel_token(token.clone())
+//!     .deadline(Instant::now() + Duration::from_secs(60));
+//! match generator.generate() {
+//!     Ok(p) => println!("{}", p),
+//!     Err(PrimeError::TimedOut) => println!("gave up after a minute"),
+//!     Err(e) => println!("{}", e),
+//! }
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+use crate::error::PrimeError;
+
+/// A flag shared between a search and whoever may want to stop it.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Asks every search holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When a running search must give up: the default never does.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stop {
+    token: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    /// The lowest settled slot of a parallel search, and the slot this
+    /// work belongs to.
+    race: Option<(Arc<AtomicU64>, u64)>,
+}
+
+impl Stop {
+    pub(crate) fn new(token: Option<CancellationToken>, deadline: Option<Instant>) -> Self {
+        Stop { token, deadline, race: None }
+    }
+
+    /// A copy that also fails with [`PrimeError::Cancelled`] once `settled`
+    /// drops below `slot`, i.e. once an earlier slot has decided the search
+    /// and this slot's work can be dropped.
+    pub(crate) fn for_slot(&self, settled: &Arc<AtomicU64>, slot: u64) -> Stop {
+        Stop { race: Some((settled.clone(), slot)), ..self.clone() }
+    }
+
+    /// Fails with [`PrimeError::Cancelled`] or [`PrimeError::TimedOut`] once
+    /// the search should stop.
+    pub(crate) fn check(&self) -> Result<(), PrimeError> {
+        if self.token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            return Err(PrimeError::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(PrimeError::TimedOut);
+        }
+        if let Some((settled, slot)) = &self.race
+            && settled.load(Ordering::Relaxed) < *slot
+        {
+            return Err(PrimeError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use num_bigint::BigUint;
+    use num_traits::One;
+
+    use super::*;
+    use crate::cert::certify_until;
+    use crate::{Parallelism, PrimeGenerator, ProofBackend, VerificationPolicy};
+
+    /// Sequential, parallel and pipelined drivers.
+    fn drivers() -> Vec<PrimeGenerator> {
+        let proven = VerificationPolicy::Proven { backend: ProofBackend::Ecpp };
+        let generator = || PrimeGenerator::new().bits(256).verification(proven);
+        vec![
+            generator().parallelism(Parallelism::Sequential),
+            generator().parallelism(Parallelism::Threads(2)),
+            generator().parallelism(Parallelism::Threads(2)).proof_threads(2),
+        ]
+    }
+
+    #[test]
+    fn cancelled_searches_return_cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+        for generator in drivers() {
+            let mut generator = generator.cancel_token(token.clone());
+            assert_eq!(generator.generate(), Err(PrimeError::Cancelled));
+            assert_eq!(generator.generate_certified().map(|_| ()), Err(PrimeError::Cancelled));
+        }
+    }
+
+    #[test]
+    fn searches_past_their_deadline_return_timed_out() {
+        let deadline = Instant::now();
+        for generator in drivers() {
+            let mut generator = generator.deadline(deadline);
+            assert_eq!(generator.generate(), Err(PrimeError::TimedOut));
+            assert_eq!(generator.generate_safe(), Err(PrimeError::TimedOut));
+        }
+    }
+
+    /// How long a stopped search may keep running. A probable-prime test
+    /// already under way is not interrupted, and a 1024-bit one takes
+    /// seconds in a debug build sharing the CPU with other tests.
+    const GRACE: Duration = Duration::from_secs(20);
+
+    /// Cancels `token` after `delay`, returning when it did.
+    fn cancel_after(token: CancellationToken, delay: Duration) -> thread::JoinHandle<Instant> {
+        thread::spawn(move || {
+            thread::sleep(delay);
+            token.cancel();
+            Instant::now()
+        })
+    }
+
+    #[test]
+    fn proofs_stop_promptly_when_cancelled() {
+        // 2^521 - 1 takes far longer than this test to prove.
+        let n = (BigUint::one() << 521) - 1u32;
+        let token = CancellationToken::new();
+        let stop = Stop::new(Some(token.clone()), None);
+        let canceller = cancel_after(token, Duration::from_millis(200));
+        assert_eq!(certify_until(&n, &stop).map(|_| ()), Err(PrimeError::Cancelled));
+        let late = canceller.join().unwrap().elapsed();
+        assert!(late < GRACE, "stopped {:?} after cancellation", late);
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let stop = Stop::new(None, Some(deadline));
+        let proof = ProofBackend::Ecpp.prove_until(&n, &stop);
+        assert_eq!(proof, Err(PrimeError::TimedOut));
+        assert!(deadline.elapsed() < GRACE, "stopped {:?} after the deadline", deadline.elapsed());
+    }
+
+    #[test]
+    fn certified_searches_stop_promptly_when_cancelled() {
+        let token = CancellationToken::new();
+        let mut generator = PrimeGenerator::new()
+            .bits(1024)
+            .parallelism(Parallelism::Threads(2))
+            .cancel_token(token.clone());
+        let canceller = cancel_after(token, Duration::from_millis(500));
+        assert_eq!(generator.generate_certified().map(|_| ()), Err(PrimeError::Cancelled));
+        let late = canceller.join().unwrap().elapsed();
+        assert!(late < GRACE, "stopped {:?} after cancellation", late);
+    }
+}
@@ -0,0 +1,144 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Pow, Zero};
+use rand::{Rng, thread_rng};
+
+use crate::cancel::Stop;
+use crate::constraint::Constraints;
+use crate::error::PrimeError;
+
+/// Size of the primes to generate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Size {
+    /// Exactly this many decimal digits.
+    Digits(usize),
+    /// Exactly `bits` bits, the top `top_bits` of which are set.
+    ///
+    /// With `top_bits: 2` the product of two such primes has exactly
+    /// `2 * bits` bits.
+    Bits { bits: usize, top_bits: usize },
+}
+
+impl Size {
+    /// Returns the half-open interval `[lower, upper)` covered by this size,
+    /// or the [`validate`](Self::validate) error for sizes without primes.
+    pub fn bounds(&self) -> Result<(BigUint, BigUint), PrimeError> {
+        self.validate()?;
+        Ok(match *self {
+            Size::Digits(digits) => (
+                BigUint::from(10u32).pow(digits as u32 - 1),
+                BigUint::from(10u32).pow(digits as u32),
+            ),
+            Size::Bits { bits, top_bits } => {
+                let top = (BigUint::one() << top_bits) - 1u32;
+                (top << (bits - top_bits), BigUint::one() << bits)
+            }
+        })
+    }
+
+    /// Returns `false` for sizes that contain no primes.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Size::Digits(digits) => digits > 0,
+            Size::Bits { bits, top_bits } => bits > 1 && (1..=bits).contains(&top_bits),
+        }
+    }
+
+    /// Fails with [`PrimeError::InvalidTopBits`] if `top_bits` does not fit
+    /// a size of at least two bits, and with [`PrimeError::InvalidDigits`]
+    /// for any other size that contains no primes.
+    pub fn validate(&self) -> Result<(), PrimeError> {
+        match *self {
+            _ if self.is_valid() => Ok(()),
+            Size::Bits { bits, top_bits } if bits > 1 => {
+                Err(PrimeError::InvalidTopBits { bits, top_bits })
+            }
+            _ => Err(PrimeError::InvalidDigits(self.value())),
+        }
+    }
+
+    /// The digit or bit count.
+    pub fn value(&self) -> usize {
+        match *self {
+            Size::Digits(n) | Size::Bits { bits: n, .. } => n,
+        }
+    }
+
+    /// Approximate size in decimal digits, used to pick tuning parameters.
+    pub fn approx_digits(&self) -> usize {
+        match *self {
+            Size::Digits(digits) => digits,
+            Size::Bits { bits, .. } => (bits as f64 * std::f64::consts::LOG10_2).ceil() as usize,
+        }
+    }
+}
+
+/// Draws a uniformly random odd number with exactly `digits` decimal digits.
+pub fn generate_prime_candidate(digits: usize) -> Result<BigUint, PrimeError> {
+    random_candidate(&mut thread_rng(), Size::Digits(digits))
+}
+
+/// Draws a uniformly random odd number with exactly `bits` bits, the top
+/// `top_bits` of which are set.
+pub fn generate_prime_candidate_bits(bits: usize, top_bits: usize) -> Result<BigUint, PrimeError> {
+    random_candidate(&mut thread_rng(), Size::Bits { bits, top_bits })
+}
+
+/// Draws a random odd number with exactly `digits` decimal digits (and in
+/// the constraint range, if one is set) satisfying `constraints`.
+pub fn generate_prime_candidate_constrained(
+    digits: usize,
+    constraints: &Constraints,
+) -> Result<BigUint, PrimeError> {
+    constraints
+        .resolve(Size::Digits(digits))?
+        .sample(&mut thread_rng(), &Stop::default())
+}
+
+/// Draws a uniformly random odd number of the given size from `rng`.
+pub fn random_candidate<R: Rng + ?Sized>(rng: &mut R, size: Size) -> Result<BigUint, PrimeError> {
+    let (lower, upper) = size.bounds()?;
+
+    loop {
+        let num = rng.gen_biguint_range(&lower, &upper);
+
+        if &num % 2u32 == BigUint::zero() {
+            let odd_num = num + BigUint::one();
+
+            if odd_num < upper {
+                return Ok(odd_num);
+            } else {
+                continue;
+            }
+        }
+
+        return Ok(num);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_without_primes_are_errors() {
+        assert_eq!(generate_prime_candidate(0), Err(PrimeError::InvalidDigits(0)));
+        assert_eq!(generate_prime_candidate_bits(0, 1), Err(PrimeError::InvalidDigits(0)));
+        assert_eq!(generate_prime_candidate_bits(1, 1), Err(PrimeError::InvalidDigits(1)));
+        for (bits, top_bits) in [(8, 0), (8, 9)] {
+            let error = PrimeError::InvalidTopBits { bits, top_bits };
+            assert_eq!(generate_prime_candidate_bits(bits, top_bits), Err(error));
+        }
+        assert!(Size::Digits(0).bounds().is_err());
+    }
+
+    #[test]
+    fn candidates_are_odd_and_in_bounds() {
+        for size in [Size::Digits(1), Size::Digits(30), Size::Bits { bits: 64, top_bits: 2 }] {
+            let (lower, upper) = size.bounds().unwrap();
+            for _ in 0..100 {
+                let candidate = random_candidate(&mut thread_rng(), size).unwrap();
+                assert!(candidate.bit(0) && lower <= candidate && candidate < upper);
+            }
+        }
+    }
+}
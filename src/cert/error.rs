@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+
+use num_bigint::BigUint;
+
+/// Why a certificate was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertificateError {
+    /// The text is not in any supported format.
+    UnknownFormat,
+    /// The text could not be parsed; `line` is 1-based.
+    Malformed { line: usize, reason: String },
+    /// A step type or format version this verifier does not implement.
+    Unsupported(String),
+    /// The certificate proves some other number.
+    WrongNumber { expected: BigUint, found: BigUint },
+    /// Step `step` (1-based) proves a number that nothing asked to be proven.
+    UnexpectedStep { step: usize, n: BigUint },
+    /// The certificate ends while `missing` still has to be proven.
+    Truncated { missing: BigUint },
+    /// Step `step` (1-based) does not prove its number.
+    InvalidStep { step: usize, reason: String },
+}
+
+impl CertificateError {
+    pub(crate) fn malformed(line: usize, reason: impl Into<String>) -> Self {
+        CertificateError::Malformed { line, reason: reason.into() }
+    }
+
+    pub(crate) fn invalid(step: usize, reason: impl Into<String>) -> Self {
+        CertificateError::InvalidStep { step, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateError::UnknownFormat => write!(f, "unrecognised certificate format"),
+            CertificateError::Malformed { line, reason } => {
+                write!(f, "malformed certificate at line {}: {}", line, reason)
+            }
+            CertificateError::Unsupported(what) => write!(f, "unsupported certificate feature: {}", what),
+            CertificateError::WrongNumber { expected, found } => {
+                write!(f, "certificate is for {}, not {}", found, expected)
+            }
+            CertificateError::UnexpectedStep { step, n } => {
+                write!(f, "step {} proves {}, which no earlier step depends on", step, n)
+            }
+            CertificateError::Truncated { missing } => {
+                write!(f, "certificate ends before {} is proven", missing)
+            }
+            CertificateError::InvalidStep { step, reason } => write!(f, "step {} is invalid: {}", step, reason),
+        }
+    }
+}
+
+impl Error for CertificateError {}
@@ -0,0 +1 @@
+[[40443887848975748132536389313, 287686112962787, 30107991, 0, [31251631583775869141549301811, 5446288929504754213378660492]], [1343294139053497805497, 70422758912, 11466, 699466399649425178706, [1334124617361795442649, 302282792449236379996]]]
@@ -0,0 +1 @@
+%1 = [19673427401324288023780576275041496250581222944755514252341, [[2, 2, 0], [5, 2, 0], [328012940240506304123479, 2, [328012940240506304123479, [[2, 3, 0], [3, 2, 0], [225593892281, 2, 0], [242332905473, 2, 0]]]]]]
@@ -0,0 +1,97 @@
+//! The `[section]` / `key = value` layout shared by the native and Primo
+//! certificate formats.
+
+use num_bigint::BigUint;
+use num_traits::Num;
+
+use super::CertificateError;
+
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub line: usize,
+    pub entries: Vec<Entry<'a>>,
+}
+
+pub struct Entry<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub line: usize,
+}
+
+/// Splits `text` into sections. Blank lines, lines starting with `#` or `;`,
+/// and everything in a `[Comments]` section are ignored.
+pub fn sections(text: &str) -> Result<Vec<Section<'_>>, CertificateError> {
+    let mut sections: Vec<Section<'_>> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| CertificateError::malformed(line, "unterminated section header"))?;
+            sections.push(Section { name: name.trim(), line, entries: Vec::new() });
+            continue;
+        }
+        if sections.last().is_some_and(|s| s.name.eq_ignore_ascii_case("Comments")) {
+            // Primo keeps free text here.
+            continue;
+        }
+        let (key, value) = trimmed
+            .split_once('=')
+            .ok_or_else(|| CertificateError::malformed(line, "expected `key = value`"))?;
+        let section = sections
+            .last_mut()
+            .ok_or_else(|| CertificateError::malformed(line, "entry before the first section"))?;
+        section.entries.push(Entry { key: key.trim(), value: value.trim(), line });
+    }
+    Ok(sections)
+}
+
+impl<'a> Section<'a> {
+    /// The single value of `key`.
+    pub fn get(&self, key: &str) -> Result<&Entry<'a>, CertificateError> {
+        let mut matches = self.entries.iter().filter(|e| e.key.eq_ignore_ascii_case(key));
+        let entry = matches.next().ok_or_else(|| {
+            CertificateError::malformed(self.line, format!("[{}] is missing `{}`", self.name, key))
+        })?;
+        if let Some(duplicate) = matches.next() {
+            return Err(CertificateError::malformed(duplicate.line, format!("duplicate `{}`", key)));
+        }
+        Ok(entry)
+    }
+
+    /// Every value of `key`, in order.
+    pub fn all(&self, key: &str) -> impl Iterator<Item = &Entry<'a>> {
+        self.entries.iter().filter(move |e| e.key.eq_ignore_ascii_case(key))
+    }
+
+    pub fn number(&self, key: &str) -> Result<BigUint, CertificateError> {
+        self.get(key)?.number()
+    }
+}
+
+impl Entry<'_> {
+    /// Parses the value as a non-negative integer: decimal, or hexadecimal
+    /// with a `$` or `0x` prefix.
+    pub fn number(&self) -> Result<BigUint, CertificateError> {
+        parse_number(self.value, self.line)
+    }
+}
+
+pub fn parse_number(value: &str, line: usize) -> Result<BigUint, CertificateError> {
+    let (digits, radix) = if let Some(hex) = value.strip_prefix('$') {
+        (hex, 16)
+    } else if let Some(hex) = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        (hex, 16)
+    } else {
+        (value, 10)
+    };
+    if digits.is_empty() {
+        return Err(CertificateError::malformed(line, "empty number"));
+    }
+    BigUint::from_str_radix(digits, radix)
+        .map_err(|_| CertificateError::malformed(line, format!("`{}` is not a number", value)))
+}
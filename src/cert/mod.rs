@@ -0,0 +1,510 @@
+//! Primality certificates.
+//!
+//! A [`Certificate`] is a flat list of steps. Each step proves one number
+//! prime assuming that the larger primes it references are proven by later
+//! steps; referenced primes that fit in a `u64` are checked directly with a
+//! deterministic test. Three kinds of step are used:
+//!
+//! * `small`: `n` fits in a `u64`.
+//! * `pocklington`: `n - 1 = F * R` with every prime `p | F` given with a
+//!   witness `a` such that `a^(n-1) = 1` and `gcd(a^((n-1)/p) - 1, n) = 1`.
+//!   `F` must satisfy `(F + 1)^2 > n`, or `F^3 > n` together with the
+//!   Brillhart–Lehmer–Selfridge condition that PARI's N-1 certificates rely
+//!   on. When `F = n - 1` this is a Pratt certificate. Used for structured
+//!   primes such as safe primes.
+//! * `ecpp`: one Atkin–Morain step, see [`EcppStep`].
+//!
+//! # Text format
+//!
+//! [`Certificate`] implements `Display` with a stable, line-oriented format
+//! made of `[section]` headers followed by `key = value` lines. All numbers are
+//! decimal. The first section is a header naming the format version and the
+//! certified number; every following section is one step, in order:
+//!
+//! ```text
+//! [large-primes certificate]
+//! version = 1
+//! n = 1000000000000000000000000000057
+//!
+//! [ecpp]
+//! n = 1000000000000000000000000000057
+//! d = -4
+//! a = ...
+//! b = ...
+//! m = ...
+//! q = ...
+//! x = ...
+//! y = ...
+//!
+//! [pocklington]
+//! n = ...
+//! factor = <p> <exponent> <witness>
+//!
+//! [small]
+//! n = 9223372036854775837
+//! ```
+//!
+//! New keys may be added to a section in later versions; existing keys keep
+//! their meaning. `d` is informational and may be omitted.
+//!
+//! [`parse_certificate`] reads this format as well as PARI/GP `primecert`
+//! output and Primo certificate files; [`verify_certificate`] re-checks any of
+//! them without searching for a proof again.
+
+mod error;
+mod ini;
+mod pari;
+mod parse;
+mod primo;
+mod verify;
+
+use std::fmt;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_prime::nt_funcs::is_prime64;
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::cancel::Stop;
+use crate::ecpp::{self, EcppStep};
+use crate::error::PrimeError;
+use crate::primality::probable_prime_check;
+use crate::sieve::SMALL_PRIMES;
+
+pub use error::CertificateError;
+pub use parse::parse_certificate;
+pub use verify::verify_certificate;
+
+/// Version written in the header of the text format.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A primality certificate for `n`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    pub n: BigUint,
+    pub steps: Vec<CertificateStep>,
+}
+
+/// One step of a [`Certificate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertificateStep {
+    /// `n` fits in a `u64` and is checked with a deterministic test.
+    Small { n: u64 },
+    /// Pocklington (or Pratt, when fully factored) `n - 1` step.
+    Pocklington(PocklingtonStep),
+    /// One Atkin–Morain ECPP step.
+    Ecpp(EcppStep),
+}
+
+/// A factored part `F` of `n - 1` large enough to prove `n`, as used by
+/// [`CertificateStep::Pocklington`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PocklingtonStep {
+    pub n: BigUint,
+    pub factors: Vec<PocklingtonFactor>,
+}
+
+/// A prime power `p^exponent` dividing `n - 1`, with its witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PocklingtonFactor {
+    pub p: BigUint,
+    pub exponent: u32,
+    pub witness: BigUint,
+}
+
+impl CertificateStep {
+    /// The number this step proves prime.
+    pub fn n(&self) -> BigUint {
+        match self {
+            CertificateStep::Small { n } => BigUint::from(*n),
+            CertificateStep::Pocklington(step) => step.n.clone(),
+            CertificateStep::Ecpp(step) => step.n.clone(),
+        }
+    }
+}
+
+/// Produces a certificate for `n`, or `None` if `n` is not prime (or no
+/// proof was found).
+///
+/// `n - 1` is tried first; if it factors far enough, the result is a
+/// Pocklington/Pratt chain. Otherwise `n` is proven with ECPP.
+pub fn certify(n: &BigUint) -> Option<Certificate> {
+    certify_until(n, &Stop::default()).unwrap_or(None)
+}
+
+/// Like [`certify`], abandoning the proof with [`PrimeError::Cancelled`] or
+/// [`PrimeError::TimedOut`] once `stop` says so.
+pub(crate) fn certify_until(n: &BigUint, stop: &Stop) -> Result<Option<Certificate>, PrimeError> {
+    let mut steps = Vec::new();
+    let proven = certify_into(n, &mut steps, stop)?;
+    Ok(proven.map(|()| Certificate { n: n.clone(), steps }))
+}
+
+fn certify_into(
+    n: &BigUint,
+    steps: &mut Vec<CertificateStep>,
+    stop: &Stop,
+) -> Result<Option<()>, PrimeError> {
+    stop.check()?;
+    if let Some(small) = n.to_u64() {
+        if !is_prime64(small) {
+            return Ok(None);
+        }
+        if steps.is_empty() {
+            steps.push(CertificateStep::Small { n: small });
+        }
+        return Ok(Some(()));
+    }
+    if !probable_prime_check(n) {
+        return Ok(None);
+    }
+
+    if let Some((step, pending)) = pocklington(n) {
+        steps.push(CertificateStep::Pocklington(step));
+        for p in pending {
+            if certify_into(&p, steps, stop)?.is_none() {
+                return Ok(None);
+            }
+        }
+        return Ok(Some(()));
+    }
+
+    let Some(chain) = ecpp::prove_until(n, stop)? else {
+        return Ok(None);
+    };
+    steps.extend(chain.into_iter().map(CertificateStep::Ecpp));
+    Ok(Some(()))
+}
+
+/// Trial-factors `n - 1`. If the factored part, plus a probable-prime
+/// cofactor if there is one, reaches `sqrt(n)`, returns the Pocklington step
+/// along with the factors that still need their own certificate.
+fn pocklington(n: &BigUint) -> Option<(PocklingtonStep, Vec<BigUint>)> {
+    let n_minus_1 = n - 1u32;
+    let mut rest = n_minus_1.clone();
+    let mut factored: Vec<(BigUint, u32)> = Vec::new();
+    for &p in SMALL_PRIMES.iter() {
+        let mut exponent = 0;
+        while (&rest % p).is_zero() {
+            rest /= p;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factored.push((BigUint::from(p), exponent));
+        }
+    }
+    if !rest.is_one() && probable_prime_check(&rest) {
+        factored.push((rest.clone(), 1));
+        rest = BigUint::one();
+    }
+
+    let f = &n_minus_1 / &rest;
+    if &f * &f < *n {
+        return None;
+    }
+
+    let mut factors = Vec::with_capacity(factored.len());
+    let mut pending = Vec::new();
+    for (p, exponent) in factored {
+        let witness = pocklington_witness(n, &p)?;
+        if p.bits() > 64 {
+            pending.push(p.clone());
+        }
+        factors.push(PocklingtonFactor { p, exponent, witness });
+    }
+    Some((PocklingtonStep { n: n.clone(), factors }, pending))
+}
+
+/// Smallest `a >= 2` with `a^(n-1) = 1` and `gcd(a^((n-1)/p) - 1, n) = 1`.
+fn pocklington_witness(n: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let n_minus_1 = n - 1u32;
+    let cofactor = &n_minus_1 / p;
+    for a in SMALL_PRIMES.iter().map(|&a| BigUint::from(a)) {
+        if !a.modpow(&n_minus_1, n).is_one() {
+            // Fermat witness: n is composite.
+            return None;
+        }
+        let x = a.modpow(&cofactor, n);
+        if !x.is_zero() && (x + &n_minus_1).gcd(n).is_one() {
+            return Some(a);
+        }
+    }
+    None
+}
+
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[large-primes certificate]")?;
+        writeln!(f, "version = {}", FORMAT_VERSION)?;
+        writeln!(f, "n = {}", self.n)?;
+        for step in &self.steps {
+            writeln!(f)?;
+            match step {
+                CertificateStep::Small { n } => {
+                    writeln!(f, "[small]")?;
+                    writeln!(f, "n = {}", n)?;
+                }
+                CertificateStep::Pocklington(step) => {
+                    writeln!(f, "[pocklington]")?;
+                    writeln!(f, "n = {}", step.n)?;
+                    for factor in &step.factors {
+                        writeln!(f, "factor = {} {} {}", factor.p, factor.exponent, factor.witness)?;
+                    }
+                }
+                CertificateStep::Ecpp(step) => {
+                    writeln!(f, "[ecpp]")?;
+                    writeln!(f, "n = {}", step.n)?;
+                    if let Some(d) = step.d {
+                        writeln!(f, "d = {}", d)?;
+                    }
+                    writeln!(f, "a = {}", step.a)?;
+                    writeln!(f, "b = {}", step.b)?;
+                    writeln!(f, "m = {}", step.m)?;
+                    writeln!(f, "q = {}", step.q)?;
+                    writeln!(f, "x = {}", step.point.x)?;
+                    writeln!(f, "y = {}", step.point.y)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use super::*;
+
+    /// A 200-bit prime whose `n - 1` does not factor far enough for
+    /// Pocklington, proven in several ECPP steps.
+    fn ecpp_prime() -> BigUint {
+        let n = "960902249212570420116466465110936637112740418072078128991377";
+        let n: BigUint = n.parse().unwrap();
+        assert!(probable_prime_check(&n) && pocklington(&n).is_none());
+        n
+    }
+
+    fn ecpp_steps<'a>(cert: &'a Certificate) -> Vec<&'a EcppStep> {
+        let ecpp = |step: &'a CertificateStep| match step {
+            CertificateStep::Ecpp(step) => step,
+            step => panic!("expected an ECPP step, got {:?}", step),
+        };
+        cert.steps.iter().map(ecpp).collect()
+    }
+
+    /// `primecert` output for the ECPP steps of `cert`.
+    fn to_pari(cert: &Certificate) -> String {
+        let entries: Vec<String> = ecpp_steps(cert)
+            .into_iter()
+            .map(|step| {
+                let t = BigInt::from(step.n.clone()) + 1u32 - BigInt::from(step.m.clone());
+                let (n, s, a, p) = (&step.n, &step.m / &step.q, &step.a, &step.point);
+                format!("[{}, {}, {}, {}, [{}, {}]]", n, t, s, a, p.x, p.y)
+            })
+            .collect();
+        format!("[{}]", entries.join(", "))
+    }
+
+    /// A Primo file for the ECPP steps of `cert`. With `T = x`, the Primo
+    /// curve is the original one scaled by `y^2`.
+    fn to_primo(cert: &Certificate) -> String {
+        let mut text = format!("{}\nFormat=4\n\n[Candidate]\nN={}\n", primo::HEADER, cert.n);
+        for (index, step) in ecpp_steps(cert).into_iter().enumerate() {
+            let w = BigInt::from(step.n.clone()) + 1u32 - BigInt::from(step.m.clone());
+            text += &format!(
+                "\n[{}]\nType=3\nS={}\nW={}\nA={}\nB={}\nT={}\n",
+                index + 1,
+                &step.m / &step.q,
+                w,
+                step.a,
+                step.b,
+                step.point.x,
+            );
+        }
+        text
+    }
+
+    #[test]
+    fn generated_primes_come_with_valid_certificates() {
+        use crate::{PrimeGenerator, VerificationPolicy};
+
+        let generator = || PrimeGenerator::new().bits(160).seed(3);
+        let (p, cert) = generator().generate_certified().unwrap();
+        verify_certificate(&p, &cert).unwrap();
+
+        // Safe primes are proven through p - 1 = 2q.
+        let safe = generator().verification(VerificationPolicy::Bpsw).generate_safe().unwrap();
+        let cert = certify(&safe).unwrap();
+        assert!(matches!(cert.steps[0], CertificateStep::Pocklington(_)));
+        verify_certificate(&safe, &cert).unwrap();
+        assert!(certify(&(&safe * &p)).is_none());
+    }
+
+    #[test]
+    fn certificates_round_trip_through_the_native_format() {
+        for n in ["18446744073709551557", "170141183460469231731687303715884105727"] {
+            let n: BigUint = n.parse().unwrap();
+            let cert = certify(&n).unwrap();
+            verify_certificate(&n, &cert).unwrap();
+            assert_eq!(parse_certificate(&cert.to_string()).unwrap(), cert);
+        }
+    }
+
+    #[test]
+    fn ecpp_certificates_round_trip_through_every_format() {
+        let n = ecpp_prime();
+        let steps = ecpp::prove(&n).unwrap().into_iter().map(CertificateStep::Ecpp).collect();
+        let cert = Certificate { n: n.clone(), steps };
+        verify_certificate(&n, &cert).unwrap();
+
+        let native = parse_certificate(&cert.to_string()).unwrap();
+        assert_eq!(native, cert);
+
+        let pari = parse_certificate(&to_pari(&cert)).unwrap();
+        verify_certificate(&n, &pari).unwrap();
+        let anonymous = |step: &EcppStep| EcppStep { d: None, ..step.clone() };
+        let expected: Vec<_> = ecpp_steps(&cert).into_iter().map(anonymous).collect();
+        assert_eq!(ecpp_steps(&pari).into_iter().cloned().collect::<Vec<_>>(), expected);
+
+        let primo = parse_certificate(&to_primo(&cert)).unwrap();
+        verify_certificate(&n, &primo).unwrap();
+        for (imported, step) in ecpp_steps(&primo).into_iter().zip(ecpp_steps(&cert)) {
+            assert_eq!((&imported.n, &imported.m, &imported.q), (&step.n, &step.m, &step.q));
+        }
+    }
+
+    /// Certificates laid out as gp prints `primecert(N)` and `primecert(N, 1)`
+    /// and as Primo writes its format 4 files. Neither program runs here, so
+    /// the fixtures were built outside this crate from CM curves and explicit
+    /// factorisations, then written in those layouts.
+    const PARI_ECPP: &str = include_str!("fixtures/pari_ecpp.gp");
+    const PARI_N_MINUS_1: &str = include_str!("fixtures/pari_n_minus_1.gp");
+    const PRIMO: &str = include_str!("fixtures/primo.out");
+
+    fn kinds(cert: &Certificate) -> Vec<&'static str> {
+        let kind = |step: &CertificateStep| match step {
+            CertificateStep::Small { .. } => "small",
+            CertificateStep::Pocklington(_) => "pocklington",
+            CertificateStep::Ecpp(_) => "ecpp",
+        };
+        cert.steps.iter().map(kind).collect()
+    }
+
+    #[test]
+    fn external_certificates_verify() {
+        for (text, n, expected) in [
+            (PARI_ECPP, "40443887848975748132536389313", &["ecpp", "ecpp"][..]),
+            (
+                PARI_N_MINUS_1,
+                "19673427401324288023780576275041496250581222944755514252341",
+                &["pocklington", "pocklington"][..],
+            ),
+            (PRIMO, "45008550517335673023796642109", &["pocklington", "ecpp", "ecpp"][..]),
+        ] {
+            let n: BigUint = n.parse().unwrap();
+            let cert = parse_certificate(text).unwrap();
+            assert_eq!(kinds(&cert), expected);
+            verify_certificate(&n, &cert).unwrap();
+        }
+
+        // The outer N-1 step only factors N - 1 past the cube root of N.
+        let cert = parse_certificate(PARI_N_MINUS_1).unwrap();
+        let CertificateStep::Pocklington(step) = &cert.steps[0] else { unreachable!() };
+        let f = step.factors.iter().fold(BigUint::one(), |f, factor| {
+            f * num_traits::pow(factor.p.clone(), factor.exponent as usize)
+        });
+        assert!((&f + 1u32).pow(2) <= cert.n && f.pow(3) > cert.n);
+        // Without the large factor, F = 20 proves nothing.
+        let mut short = cert.clone();
+        let CertificateStep::Pocklington(step) = &mut short.steps[0] else { unreachable!() };
+        step.factors.pop();
+        assert!(matches!(
+            verify_certificate(&cert.n, &short),
+            Err(CertificateError::InvalidStep { step: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn shared_dependencies_are_proven_once() {
+        // Both factors of N - 1 depend on the same 67-bit prime, whose step
+        // comes before the second of them.
+        let text = concat!(
+            "[large-primes certificate]\n",
+            "version = 1\n",
+            "n = 19992269621338976720876212541442680536719547\n",
+            "\n",
+            "[pocklington]\n",
+            "n = 19992269621338976720876212541442680536719547\n",
+            "factor = 2 1 2\n",
+            "factor = 3 2 2\n",
+            "factor = 442721857769029240063 1 2\n",
+            "factor = 2508757194024499027019 1 2\n",
+            "\n",
+            "[pocklington]\n",
+            "n = 442721857769029240063\n",
+            "factor = 2 1 3\n",
+            "factor = 3 1 3\n",
+            "factor = 73786976294838206677 1 2\n",
+            "\n",
+            "[pocklington]\n",
+            "n = 73786976294838206677\n",
+            "factor = 2 2 2\n",
+            "factor = 3 1 2\n",
+            "factor = 6148914691236517223 1 2\n",
+            "\n",
+            "[pocklington]\n",
+            "n = 2508757194024499027019\n",
+            "factor = 2 1 2\n",
+            "factor = 17 1 2\n",
+            "factor = 73786976294838206677 1 2\n",
+        );
+        let cert = parse_certificate(text).unwrap();
+        verify_certificate(&cert.n, &cert).unwrap();
+    }
+
+    #[test]
+    fn forged_certificates_are_rejected() {
+        let header = |n: u32| format!("[large-primes certificate]\nversion = 1\nn = {}\n\n", n);
+        for (n, step) in [
+            (1, "[small]\nn = 1\n"),
+            (25, "[small]\nn = 25\n"),
+            // 24 = 2^3 * 3, but 2^24 != 1 (mod 25).
+            (25, "[pocklington]\nn = 25\nfactor = 2 3 2\n"),
+        ] {
+            let cert = parse_certificate(&(header(n) + step)).unwrap();
+            assert!(verify_certificate(&BigUint::from(n), &cert).is_err(), "{}", step);
+        }
+
+        let n = ecpp_prime();
+        let cert = certify(&n).unwrap();
+        assert!(matches!(
+            verify_certificate(&(&n + 2u32), &cert),
+            Err(CertificateError::WrongNumber { .. })
+        ));
+
+        let mut moved = cert.clone();
+        let CertificateStep::Ecpp(step) = &mut moved.steps[0] else { unreachable!() };
+        step.point.x += 1u32;
+        assert!(verify_certificate(&n, &moved).is_err());
+    }
+
+    #[test]
+    fn truncated_certificates_are_rejected() {
+        let n = ecpp_prime();
+        let cert = certify(&n).unwrap();
+        assert!(cert.steps.len() > 1);
+        let mut truncated = cert.clone();
+        truncated.steps.pop();
+        assert!(matches!(
+            verify_certificate(&n, &truncated),
+            Err(CertificateError::Truncated { .. })
+        ));
+
+        let text = cert.to_string();
+        let header = &text[..text.find("\n\n").unwrap() + 1];
+        assert!(matches!(parse_certificate(header), Err(CertificateError::Truncated { .. })));
+        let pari = to_pari(&cert);
+        assert!(parse_certificate(&pari[..pari.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! PARI/GP `primecert` output.
+//!
+//! Two shapes are accepted, as printed by `print(primecert(N))` and
+//! `print(primecert(N, 1))`:
+//!
+//! * ECPP: `[[N, t, s, a4, [x, y]], ...]`. The curve is
+//!   `y^2 = x^3 + a4 x + b` with `b` implied by the point, its order is
+//!   `m = N + 1 - t = s * q`, and `q` is the `N` of the next entry. The last
+//!   `q` must fit in 64 bits.
+//! * N-1: `[N, [[p, a, C], ...]]`, where `a` is the witness for `p` and `C`
+//!   is a certificate of the same shape for `p`, or `0` for small `p`.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+
+use super::{Certificate, CertificateError, CertificateStep, PocklingtonFactor, PocklingtonStep};
+use crate::arith::{mod_sub, to_residue};
+use crate::ecpp::EcppStep;
+use crate::ecpp::curve::Point;
+
+/// A parsed GP value and the line it starts on.
+struct Value {
+    line: usize,
+    kind: Kind,
+}
+
+enum Kind {
+    Int(BigInt),
+    Vec(Vec<Value>),
+}
+
+/// Returns `true` for GP output such as `[[...` or `%1 = [...`.
+pub fn looks_like_pari(first_line: &str) -> bool {
+    let line = strip_history(first_line);
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    chars.next() == Some('[') && matches!(chars.next(), Some('[' | '0'..='9' | '-'))
+}
+
+fn strip_history(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    match trimmed.strip_prefix('%') {
+        Some(rest) => rest.split_once('=').map_or(trimmed, |(_, value)| value.trim_start()),
+        None => trimmed,
+    }
+}
+
+pub fn parse(text: &str) -> Result<Certificate, CertificateError> {
+    let mut parser = Parser::new(text);
+    parser.skip_history();
+    let value = parser.value()?;
+    parser.expect_end()?;
+
+    let items = as_vec(&value)?;
+    match items.first().map(|item| &item.kind) {
+        Some(Kind::Vec(_)) => parse_ecpp(items),
+        Some(Kind::Int(_)) => {
+            let mut steps = Vec::new();
+            let n = parse_n_minus_1(&value, &mut steps)?;
+            Ok(Certificate { n, steps })
+        }
+        None => Err(CertificateError::malformed(value.line, "empty certificate")),
+    }
+}
+
+fn parse_ecpp(entries: &[Value]) -> Result<Certificate, CertificateError> {
+    let mut steps = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let step = index + 1;
+        let [n, t, s, a4, point] = as_vec(entry)? else {
+            return Err(CertificateError::malformed(entry.line, "ECPP entry must be [N, t, s, a4, [x, y]]"));
+        };
+        let [x, y] = as_vec(point)? else {
+            return Err(CertificateError::malformed(point.line, "ECPP point must be [x, y]"));
+        };
+        let n = as_natural(n)?;
+        if n.is_zero() {
+            return Err(CertificateError::invalid(step, "N is zero"));
+        }
+        let a = to_residue(as_int(a4)?, &n);
+        let x = to_residue(as_int(x)?, &n);
+        let y = to_residue(as_int(y)?, &n);
+        let x2 = &x * &x % &n;
+        let b = mod_sub(&(&y * &y % &n), &((x2 * &x + &a * &x) % &n), &n);
+
+        let m = BigInt::from_biguint(Sign::Plus, n.clone()) + 1u32 - as_int(t)?;
+        let m = m
+            .to_biguint()
+            .filter(|m| !m.is_zero())
+            .ok_or_else(|| CertificateError::invalid(step, "N + 1 - t is not positive"))?;
+        let s = as_natural(s)?;
+        if s.is_zero() {
+            return Err(CertificateError::invalid(step, "s is zero"));
+        }
+        let (q, rem) = m.div_rem(&s);
+        if !rem.is_zero() {
+            return Err(CertificateError::invalid(step, "s does not divide N + 1 - t"));
+        }
+        steps.push(CertificateStep::Ecpp(EcppStep { n, d: None, a, b, m, q, point: Point { x, y } }));
+    }
+    let n = steps[0].n();
+    Ok(Certificate { n, steps })
+}
+
+/// Flattens a nested N-1 certificate into `steps`, returning its `N`.
+fn parse_n_minus_1(value: &Value, steps: &mut Vec<CertificateStep>) -> Result<BigUint, CertificateError> {
+    let [n, factors] = as_vec(value)? else {
+        return Err(CertificateError::malformed(value.line, "N-1 certificate must be [N, [[p, a, C], ...]]"));
+    };
+    let n = as_natural(n)?;
+    let n_minus_1 = if n.is_zero() { BigUint::zero() } else { &n - 1u32 };
+    let index = steps.len();
+    steps.push(CertificateStep::Small { n: 0 });
+
+    let mut parsed = Vec::new();
+    for factor in as_vec(factors)? {
+        let [p, a, sub] = as_vec(factor)? else {
+            return Err(CertificateError::malformed(factor.line, "N-1 factor must be [p, a, C]"));
+        };
+        let p = as_natural(p)?;
+        let witness = as_natural(a)?;
+        let mut exponent = 0;
+        let mut rest = n_minus_1.clone();
+        while p > BigUint::from(1u32) && !rest.is_zero() && (&rest % &p).is_zero() {
+            rest /= &p;
+            exponent += 1;
+        }
+        if exponent == 0 {
+            return Err(CertificateError::invalid(index + 1, format!("{} does not divide N - 1", p)));
+        }
+        match &sub.kind {
+            Kind::Int(zero) if zero.is_zero() => {}
+            _ => {
+                let proven = parse_n_minus_1(sub, steps)?;
+                if proven != p {
+                    return Err(CertificateError::malformed(sub.line, format!("sub-certificate for {} proves {}", p, proven)));
+                }
+            }
+        }
+        parsed.push(PocklingtonFactor { p, exponent, witness });
+    }
+    steps[index] = CertificateStep::Pocklington(PocklingtonStep { n: n.clone(), factors: parsed });
+    Ok(n)
+}
+
+fn as_vec(value: &Value) -> Result<&[Value], CertificateError> {
+    match &value.kind {
+        Kind::Vec(items) => Ok(items),
+        Kind::Int(_) => Err(CertificateError::malformed(value.line, "expected a vector")),
+    }
+}
+
+fn as_int(value: &Value) -> Result<&BigInt, CertificateError> {
+    match &value.kind {
+        Kind::Int(n) => Ok(n),
+        Kind::Vec(_) => Err(CertificateError::malformed(value.line, "expected an integer")),
+    }
+}
+
+fn as_natural(value: &Value) -> Result<BigUint, CertificateError> {
+    let n = as_int(value)?;
+    if n.is_negative() {
+        return Err(CertificateError::malformed(value.line, format!("expected a non-negative integer, got {}", n)));
+    }
+    Ok(n.magnitude().clone())
+}
+
+/// Deepest vector nesting accepted. Real certificates nest a few levels
+/// per N-1 step; this only keeps hostile input from exhausting the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    /// Vectors currently open.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser { chars: text.chars().peekable(), line: 1, depth: 0 }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn next_if(&mut self, pred: impl FnOnce(char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(&c) if pred(c) => self.next(),
+            _ => None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.next_if(char::is_whitespace).is_some() {}
+    }
+
+    fn skip_history(&mut self) {
+        self.skip_whitespace();
+        if self.next_if(|c| c == '%').is_some() {
+            while self.next_if(|c| c != '=').is_some() {}
+            self.next();
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), CertificateError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) => Err(CertificateError::malformed(self.line, format!("unexpected `{}` after certificate", c))),
+            None => Ok(()),
+        }
+    }
+
+    fn truncated(&self) -> CertificateError {
+        CertificateError::malformed(self.line, "unexpected end of input")
+    }
+
+    /// The items of a vector whose `[` has been read, through its `]`.
+    fn items(&mut self) -> Result<Vec<Value>, CertificateError> {
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.next_if(|c| c == ']').is_some() {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',' | ';') => continue,
+                Some(']') => return Ok(items),
+                Some(c) => {
+                    return Err(CertificateError::malformed(self.line, format!("expected `,` or `]`, found `{}`", c)));
+                }
+                None => return Err(self.truncated()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, CertificateError> {
+        self.skip_whitespace();
+        let line = self.line;
+        match self.next() {
+            Some('[') => {
+                if self.depth == MAX_DEPTH {
+                    return Err(CertificateError::malformed(line, "vectors nested too deeply"));
+                }
+                self.depth += 1;
+                let items = self.items();
+                self.depth -= 1;
+                Ok(Value { line, kind: Kind::Vec(items?) })
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut digits = String::from(c);
+                while let Some(d) = self.next_if(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                }
+                digits
+                    .parse()
+                    .map(|n| Value { line, kind: Kind::Int(n) })
+                    .map_err(|_| CertificateError::malformed(line, format!("`{}` is not an integer", digits)))
+            }
+            Some(c) => Err(CertificateError::malformed(line, format!("unexpected `{}`", c))),
+            None => Err(self.truncated()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let text = "[".repeat(1_000_000);
+        assert!(matches!(parse(&text), Err(CertificateError::Malformed { .. })));
+        let parsed = crate::cert::parse_certificate(&text);
+        assert!(matches!(parsed, Err(CertificateError::Malformed { .. })));
+        let closed = format!("{}1{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        assert!(matches!(parse(&closed), Err(CertificateError::Malformed { .. })));
+    }
+
+    #[test]
+    fn moderate_nesting_still_parses() {
+        let text = format!("{}1{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        let mut parser = Parser::new(&text);
+        assert!(parser.value().is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use super::ini::{self, Section};
+use super::{
+    Certificate, CertificateError, CertificateStep, FORMAT_VERSION, PocklingtonFactor,
+    PocklingtonStep, pari, primo,
+};
+use crate::ecpp::EcppStep;
+use crate::ecpp::curve::Point;
+
+const HEADER: &str = "large-primes certificate";
+
+/// Parses a certificate in the native text format, PARI/GP `primecert`
+/// output, or a Primo certificate file.
+pub fn parse_certificate(text: &str) -> Result<Certificate, CertificateError> {
+    let first = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .ok_or(CertificateError::UnknownFormat)?;
+
+    if first.eq_ignore_ascii_case(&format!("[{}]", HEADER)) {
+        parse_native(text)
+    } else if first.eq_ignore_ascii_case(primo::HEADER) {
+        primo::parse(text)
+    } else if pari::looks_like_pari(first) {
+        pari::parse(text)
+    } else {
+        Err(CertificateError::UnknownFormat)
+    }
+}
+
+fn parse_native(text: &str) -> Result<Certificate, CertificateError> {
+    let sections = ini::sections(text)?;
+    let (header, steps) = sections.split_first().ok_or(CertificateError::UnknownFormat)?;
+
+    let version_entry = header.get("version")?;
+    let version = version_entry.number()?;
+    if version > BigUint::from(FORMAT_VERSION) {
+        return Err(CertificateError::Unsupported(format!("format version {}", version)));
+    }
+    let n = header.number("n")?;
+    if steps.is_empty() {
+        return Err(CertificateError::Truncated { missing: n });
+    }
+
+    let steps = steps.iter().map(parse_step).collect::<Result<_, _>>()?;
+    Ok(Certificate { n, steps })
+}
+
+fn parse_step(section: &Section<'_>) -> Result<CertificateStep, CertificateError> {
+    match section.name {
+        "small" => {
+            let n = section.number("n")?;
+            let n = n
+                .to_u64()
+                .ok_or_else(|| CertificateError::malformed(section.line, "[small] number does not fit in 64 bits"))?;
+            Ok(CertificateStep::Small { n })
+        }
+        "pocklington" => {
+            let n = section.number("n")?;
+            let mut factors = Vec::new();
+            for entry in section.all("factor") {
+                let fields: Vec<&str> = entry.value.split_whitespace().collect();
+                let &[p, exponent, witness] = fields.as_slice() else {
+                    return Err(CertificateError::malformed(entry.line, "expected `factor = <p> <exponent> <witness>`"));
+                };
+                let exponent = exponent
+                    .parse()
+                    .map_err(|_| CertificateError::malformed(entry.line, format!("bad exponent `{}`", exponent)))?;
+                factors.push(PocklingtonFactor {
+                    p: ini::parse_number(p, entry.line)?,
+                    exponent,
+                    witness: ini::parse_number(witness, entry.line)?,
+                });
+            }
+            if factors.is_empty() {
+                return Err(CertificateError::malformed(section.line, "[pocklington] has no factors"));
+            }
+            Ok(CertificateStep::Pocklington(PocklingtonStep { n, factors }))
+        }
+        "ecpp" => {
+            let d = match section.all("d").next() {
+                Some(entry) => Some(entry.value.parse().map_err(|_| {
+                    CertificateError::malformed(entry.line, format!("bad discriminant `{}`", entry.value))
+                })?),
+                None => None,
+            };
+            Ok(CertificateStep::Ecpp(EcppStep {
+                n: section.number("n")?,
+                d,
+                a: section.number("a")?,
+                b: section.number("b")?,
+                m: section.number("m")?,
+                q: section.number("q")?,
+                point: Point { x: section.number("x")?, y: section.number("y")? },
+            }))
+        }
+        other => Err(CertificateError::Unsupported(format!("step type [{}]", other))),
+    }
+}
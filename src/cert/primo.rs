@@ -0,0 +1,129 @@
+//! Primo certificate files (format 4).
+//!
+//! The `[Candidate]` section gives `N`; sections `[1]`, `[2]`, ... are the
+//! steps in order, each proving the current `N` assuming the next one, `R`.
+//! Supported step types:
+//!
+//! * `Type=2` (N-1 test): `N - 1 = S * R`, witness `B`.
+//! * `Type=3` (EC test): curve `y^2 = x^3 + Ax + B`.
+//! * `Type=4` (EC test): curve given by its invariant `J`, with
+//!   `A = 3J(1728 - J)` and `B = 2J(1728 - J)^2`.
+//!
+//! For the EC tests `N + 1 - W = S * R`, and the point is derived from `T`:
+//! with `L = T^3 + AT + B`, the certificate's curve is
+//! `y^2 = x^3 + AL^2 x + BL^3` and the point is `(TL, L^2)`. The final `R`
+//! must fit in 64 bits.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::Zero;
+
+use super::ini::{self, Section};
+use super::{Certificate, CertificateError, CertificateStep, PocklingtonFactor, PocklingtonStep};
+use crate::arith::mod_sub;
+use crate::ecpp::EcppStep;
+use crate::ecpp::curve::Point;
+
+pub const HEADER: &str = "[PRIMO - Primality Certificate]";
+
+pub fn parse(text: &str) -> Result<Certificate, CertificateError> {
+    let sections = ini::sections(text)?;
+    let header = sections.first().ok_or(CertificateError::UnknownFormat)?;
+    let format = header.get("Format")?;
+    if format.value != "4" {
+        return Err(CertificateError::Unsupported(format!("Primo format {}", format.value)));
+    }
+
+    let candidate = sections
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case("Candidate"))
+        .ok_or_else(|| CertificateError::malformed(header.line, "missing [Candidate] section"))?;
+    let n = candidate.number("N")?;
+
+    let mut steps = Vec::new();
+    let mut current = n.clone();
+    for section in sections.iter().filter(|s| s.name.parse::<usize>().is_ok()) {
+        let expected = steps.len() + 1;
+        if section.name.parse::<usize>() != Ok(expected) {
+            return Err(CertificateError::malformed(
+                section.line,
+                format!("expected section [{}], found [{}]", expected, section.name),
+            ));
+        }
+        let step = parse_step(section, &current)?;
+        current = match &step {
+            CertificateStep::Pocklington(step) => step.factors[0].p.clone(),
+            CertificateStep::Ecpp(step) => step.q.clone(),
+            CertificateStep::Small { .. } => unreachable!(),
+        };
+        steps.push(step);
+    }
+    if steps.is_empty() {
+        return Err(CertificateError::Truncated { missing: n });
+    }
+    Ok(Certificate { n, steps })
+}
+
+fn parse_step(section: &Section<'_>, n: &BigUint) -> Result<CertificateStep, CertificateError> {
+    let kind = section.get("Type")?;
+    let s = section.number("S")?;
+    if s.is_zero() {
+        return Err(CertificateError::malformed(section.line, "S is zero"));
+    }
+    match kind.value {
+        "2" => {
+            let (r, rem) = (n - 1u32).div_rem(&s);
+            if !rem.is_zero() {
+                return Err(CertificateError::malformed(section.line, "S does not divide N - 1"));
+            }
+            let witness = section.number("B")?;
+            Ok(CertificateStep::Pocklington(PocklingtonStep {
+                n: n.clone(),
+                factors: vec![PocklingtonFactor { p: r, exponent: 1, witness }],
+            }))
+        }
+        "3" | "4" => {
+            let (a, b) = if kind.value == "3" {
+                (section.number("A")? % n, section.number("B")? % n)
+            } else {
+                let j = section.number("J")? % n;
+                let c = mod_sub(&(BigUint::from(1728u32) % n), &j, n);
+                let a = (&j * &c % n) * 3u32 % n;
+                let b = (&j * &c % n) * &c % n * 2u32 % n;
+                (a, b)
+            };
+            let t = section.number("T")? % n;
+            let l = ((&t * &t % n) * &t + &a * &t + &b) % n;
+            let l2 = &l * &l % n;
+            let l3 = &l2 * &l % n;
+
+            let w = section.get("W")?;
+            let w = parse_signed(w.value, w.line)?;
+            let m = (BigInt::from_biguint(Sign::Plus, n.clone()) + 1u32 - w)
+                .to_biguint()
+                .filter(|m| !m.is_zero())
+                .ok_or_else(|| CertificateError::malformed(section.line, "N + 1 - W is not positive"))?;
+            let (q, rem) = m.div_rem(&s);
+            if !rem.is_zero() {
+                return Err(CertificateError::malformed(section.line, "S does not divide N + 1 - W"));
+            }
+            Ok(CertificateStep::Ecpp(EcppStep {
+                n: n.clone(),
+                d: None,
+                a: a * &l2 % n,
+                b: b * &l3 % n,
+                m,
+                q,
+                point: Point { x: t * &l % n, y: l2 },
+            }))
+        }
+        other => Err(CertificateError::Unsupported(format!("Primo step Type={}", other))),
+    }
+}
+
+fn parse_signed(value: &str, line: usize) -> Result<BigInt, CertificateError> {
+    match value.strip_prefix('-') {
+        Some(magnitude) => Ok(-BigInt::from(ini::parse_number(magnitude, line)?)),
+        None => Ok(BigInt::from(ini::parse_number(value, line)?)),
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_prime::nt_funcs::is_prime64;
+use num_traits::{One, ToPrimitive, Zero};
+
+use super::{Certificate, CertificateError, CertificateStep, PocklingtonStep};
+use crate::ecpp::EcppStep;
+use crate::ecpp::curve::Curve;
+
+/// Checks that `cert` proves `n` prime.
+///
+/// Every step must prove either `n` itself (the first step) or a prime that
+/// an earlier step depends on, and every such prime must be proven by some
+/// step, unless it fits in a `u64`, in which case it is tested directly. A
+/// prime that several steps depend on only needs proving once.
+pub fn verify_certificate(n: &BigUint, cert: &Certificate) -> Result<(), CertificateError> {
+    if &cert.n != n {
+        return Err(CertificateError::WrongNumber { expected: n.clone(), found: cert.n.clone() });
+    }
+    let first = cert.steps.first().ok_or_else(|| CertificateError::Truncated { missing: n.clone() })?;
+    if &first.n() != n {
+        return Err(CertificateError::WrongNumber { expected: n.clone(), found: first.n() });
+    }
+
+    let mut pending = BTreeSet::from([n.clone()]);
+    let mut proven = BTreeSet::new();
+    for (index, step) in cert.steps.iter().enumerate() {
+        let number = step.n();
+        let step_no = index + 1;
+        if !pending.remove(&number) {
+            return Err(CertificateError::UnexpectedStep { step: step_no, n: number });
+        }
+        let dependencies = match step {
+            CertificateStep::Small { n } => {
+                if !is_prime64(*n) {
+                    return Err(CertificateError::invalid(step_no, format!("{} is not prime", n)));
+                }
+                Vec::new()
+            }
+            CertificateStep::Pocklington(step) => verify_pocklington(step, step_no)?,
+            CertificateStep::Ecpp(step) => verify_ecpp(step, step_no)?,
+        };
+        for p in dependencies {
+            match p.to_u64() {
+                Some(small) if !is_prime64(small) => {
+                    return Err(CertificateError::invalid(step_no, format!("{} is not prime", small)));
+                }
+                Some(_) => {}
+                None if proven.contains(&p) => {}
+                None => {
+                    pending.insert(p);
+                }
+            }
+        }
+        proven.insert(number);
+    }
+
+    match pending.into_iter().next() {
+        Some(missing) => Err(CertificateError::Truncated { missing }),
+        None => Ok(()),
+    }
+}
+
+/// Checks one Pocklington step, returning the primes it depends on.
+///
+/// With `F` the factored part of `N - 1`, every prime factor of `N` is
+/// `1 mod F`. That proves `N` prime when `(F + 1)^2 > N`, or, as PARI's N-1
+/// certificates allow, when `F^3 > N` and the Brillhart-Lehmer-Selfridge
+/// test in [`bls_cube_root`] passes.
+fn verify_pocklington(step: &PocklingtonStep, step_no: usize) -> Result<Vec<BigUint>, CertificateError> {
+    let n = &step.n;
+    if n < &BigUint::from(3u32) || n.is_even() {
+        return Err(CertificateError::invalid(step_no, "N must be odd and at least 3"));
+    }
+    let n_minus_1 = n - 1u32;
+    let mut f = BigUint::one();
+    for factor in &step.factors {
+        let p = &factor.p;
+        if p < &BigUint::from(2u32) || factor.exponent == 0 {
+            return Err(CertificateError::invalid(step_no, format!("bad factor {}^{}", p, factor.exponent)));
+        }
+        f *= num_traits::pow(p.clone(), factor.exponent as usize);
+        if !(&n_minus_1 % &f).is_zero() {
+            return Err(CertificateError::invalid(step_no, format!("{}^{} does not divide N - 1", p, factor.exponent)));
+        }
+        let a = &factor.witness;
+        if !a.modpow(&n_minus_1, n).is_one() {
+            return Err(CertificateError::invalid(step_no, format!("witness {} fails a^(N-1) = 1", a)));
+        }
+        let x = a.modpow(&(&n_minus_1 / p), n);
+        if x.is_zero() || !(x + &n_minus_1).gcd(n).is_one() {
+            return Err(CertificateError::invalid(
+                step_no,
+                format!("witness {} fails gcd(a^((N-1)/{}) - 1, N) = 1", a, p),
+            ));
+        }
+    }
+    let f1 = &f + 1u32;
+    if &f1 * &f1 <= *n && !bls_cube_root(n, &f) {
+        return Err(CertificateError::invalid(step_no, "factored part of N - 1 is too small"));
+    }
+    Ok(step.factors.iter().map(|factor| factor.p.clone()).collect())
+}
+
+/// Brillhart-Lehmer-Selfridge (1975), Theorem 5: given `N - 1 = F R`, prime
+/// factors of `N` that are all `1 mod F`, and `F^3 > N`, write
+/// `N = c2 F^2 + c1 F + 1` in base `F`. `N` is then prime unless
+/// `c1^2 - 4 c2` is a perfect square, in which case
+/// `N = (a F + 1)(b F + 1)` with `a + b = c1` and `a b = c2`.
+fn bls_cube_root(n: &BigUint, f: &BigUint) -> bool {
+    if f * f * f <= *n {
+        return false;
+    }
+    let (c2, c1) = ((n - 1u32) / f).div_rem(f);
+    let c1_squared = &c1 * &c1;
+    let four_c2 = c2 * 4u32;
+    if c1_squared < four_c2 {
+        return true;
+    }
+    let d = c1_squared - four_c2;
+    let root = d.sqrt();
+    &root * &root != d
+}
+
+/// Checks one ECPP step, returning `q`.
+fn verify_ecpp(step: &EcppStep, step_no: usize) -> Result<Vec<BigUint>, CertificateError> {
+    let n = &step.n;
+    if !n.gcd(&BigUint::from(6u32)).is_one() {
+        return Err(CertificateError::invalid(step_no, "N must be coprime to 6"));
+    }
+    let q = &step.q;
+    if !q_exceeds_bound(q, n) {
+        return Err(CertificateError::invalid(step_no, "q is not above (N^(1/4) + 1)^2"));
+    }
+    let (k, rem) = step.m.div_rem(q);
+    if !rem.is_zero() {
+        return Err(CertificateError::invalid(step_no, "q does not divide m"));
+    }
+
+    let curve = Curve::new(step.a.clone(), step.b.clone(), n.clone());
+    if !curve.is_nonsingular() {
+        return Err(CertificateError::invalid(step_no, "curve is singular modulo N"));
+    }
+    if !curve.contains(&step.point) {
+        return Err(CertificateError::invalid(step_no, "point is not on the curve"));
+    }
+    let u = match curve.mul(&step.point, &k) {
+        Ok(Some(u)) => u,
+        Ok(None) => return Err(CertificateError::invalid(step_no, "(m/q) P is the point at infinity")),
+        Err(_) => return Err(CertificateError::invalid(step_no, "N is composite (non-invertible element in (m/q) P)")),
+    };
+    match curve.mul(&u, q) {
+        Ok(None) => Ok(vec![q.clone()]),
+        Ok(Some(_)) => Err(CertificateError::invalid(step_no, "m P is not the point at infinity")),
+        Err(_) => Err(CertificateError::invalid(step_no, "N is composite (non-invertible element in m P)")),
+    }
+}
+
+/// Returns `true` if `q > (n^(1/4) + 1)^2`, computed exactly.
+///
+/// With `s = sqrt(q)`, the condition is `(s - 1)^4 > n`, which expands to
+/// `q^2 + 6q + 1 - n > 4 (q + 1) s`.
+pub fn q_exceeds_bound(q: &BigUint, n: &BigUint) -> bool {
+    if q <= &BigUint::one() {
+        return false;
+    }
+    let lhs = q * q + q * 6u32 + 1u32;
+    if &lhs <= n {
+        return false;
+    }
+    let lhs = lhs - n;
+    let q1 = q + 1u32;
+    &lhs * &lhs > (&q1 * &q1) * q * 16u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_root_test_rejects_products_of_factors_1_mod_f() {
+        let f = BigUint::from(1000u32);
+        // (3F + 1)(5F + 1) = 15 F^2 + 8 F + 1, and 8^2 - 4 * 15 = 2^2.
+        let composite = (&f * 3u32 + 1u32) * (&f * 5u32 + 1u32);
+        assert!(!bls_cube_root(&composite, &f));
+        // 15 F^2 + 10 F + 1 = 15010001 is prime, and 10^2 - 4 * 15 = 40.
+        let prime = &f * &f * 15u32 + &f * 10u32 + 1u32;
+        assert!(is_prime64(prime.to_u64().unwrap()) && bls_cube_root(&prime, &f));
+        // Below the cube root nothing is decided.
+        assert!(!bls_cube_root(&(&f * &f * &f + 1u32), &f));
+    }
+}
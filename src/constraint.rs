@@ -0,0 +1,282 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::Rng;
+
+use crate::arith::{mod_inverse, mod_sub};
+use crate::cancel::Stop;
+use crate::candidate::Size;
+use crate::error::PrimeError;
+
+/// Extra conditions on generated primes.
+///
+/// Residue classes are combined by the Chinese remainder theorem into a single
+/// progression `a + k * m`, and candidates are drawn directly from it, so a
+/// class like `p = 1 mod 2^k` costs nothing extra. Excluded residues are
+/// skipped by stepping along the progression.
+///
+/// ```
+/// use large_primes::Constraints;
+///
+/// // Blum primes: p = 3 mod 4, and p - 1 not divisible by 5.
+/// let blum = Constraints::new().residue(3u32, 4u32).exclude(1, 5);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Constraints {
+    range: Option<(BigUint, BigUint)>,
+    residues: Vec<(BigUint, BigUint)>,
+    excluded: Vec<(u64, u64)>,
+}
+
+impl Constraints {
+    /// No constraints beyond the size.
+    pub fn new() -> Self {
+        Constraints::default()
+    }
+
+    /// Only primes in `[lo, hi)`. The range narrows the bounds given by the
+    /// size rather than replacing them, so a range that misses them leaves
+    /// nothing and fails with [`PrimeError::Unsatisfiable`].
+    pub fn range(mut self, lo: impl Into<BigUint>, hi: impl Into<BigUint>) -> Self {
+        self.range = Some((lo.into(), hi.into()));
+        self
+    }
+
+    /// Only primes `p = a (mod m)`. May be given several times.
+    pub fn residue(mut self, a: impl Into<BigUint>, m: impl Into<BigUint>) -> Self {
+        self.residues.push((a.into(), m.into()));
+        self
+    }
+
+    /// No primes `p = r (mod m)`. May be given several times.
+    pub fn exclude(mut self, r: u64, m: u64) -> Self {
+        self.excluded.push((r, m));
+        self
+    }
+
+    /// Combines the constraints with `size` into the progression candidates
+    /// are drawn from.
+    pub(crate) fn resolve(&self, size: Size) -> Result<Space, PrimeError> {
+        let (lo, hi) = size.bounds()?;
+        self.resolve_within(lo, hi, size.approx_digits())
+    }
+
+    /// Like [`resolve`](Self::resolve), for bounds `[lo, hi)` that no
+    /// [`Size`] describes, with candidates of about `digits` digits.
+    pub(crate) fn resolve_within(
+        &self,
+        lo: BigUint,
+        hi: BigUint,
+        digits: usize,
+    ) -> Result<Space, PrimeError> {
+        let (lo, hi) = match &self.range {
+            Some((range_lo, range_hi)) => (lo.max(range_lo.clone()), hi.min(range_hi.clone())),
+            None => (lo, hi),
+        };
+
+        // Every prime worth generating is odd.
+        let (mut a, mut m) = (BigUint::one(), BigUint::from(2u32));
+        for (a2, m2) in &self.residues {
+            (a, m) = crt(&a, &m, a2, m2).ok_or(PrimeError::Unsatisfiable)?;
+        }
+        if !a.gcd(&m).is_one() {
+            return Err(PrimeError::Unsatisfiable);
+        }
+        if self.excluded.iter().any(|&(_, m)| m == 0) {
+            return Err(PrimeError::Unsatisfiable);
+        }
+
+        let first = &lo + mod_sub(&a, &(&lo % &m), &m);
+        if first >= hi {
+            return Err(PrimeError::Unsatisfiable);
+        }
+        let count = (&hi - 1u32 - &first) / &m + 1u32;
+
+        // Modulo each excluded modulus the progression repeats, so whether any
+        // point survives the exclusions shows within one combined period.
+        let mut period = BigUint::one();
+        for &(_, em) in &self.excluded {
+            let em = BigUint::from(em);
+            period = period.lcm(&(&em / em.gcd(&m)));
+        }
+        let span = (&period * 2u32).min(count.clone());
+        let space = Space {
+            first,
+            step: m,
+            count,
+            excluded: self.excluded.clone(),
+            digits,
+            span,
+        };
+        if let Some(scan) = period.min(space.count.clone()).to_u64()
+            && scan <= MAX_EXCLUSION_SCAN
+            && !(0..scan).any(|k| space.admits(&space.nth(&BigUint::from(k))))
+        {
+            return Err(PrimeError::Unsatisfiable);
+        }
+        Ok(space)
+    }
+}
+
+/// Longest exclusion period [`Constraints::resolve`] checks for full coverage.
+/// Longer periods are left to [`Space::sample`], which gives up after two.
+const MAX_EXCLUSION_SCAN: u64 = 1 << 16;
+
+/// Solves `x = a1 (mod m1)`, `x = a2 (mod m2)`, returning `(x, lcm(m1, m2))`.
+fn crt(a1: &BigUint, m1: &BigUint, a2: &BigUint, m2: &BigUint) -> Option<(BigUint, BigUint)> {
+    if m2.is_zero() {
+        return None;
+    }
+    let g = m1.gcd(m2);
+    let a2 = a2 % m2;
+    let diff = mod_sub(&a2, &(a1 % m2), m2);
+    if !(&diff % &g).is_zero() {
+        return None;
+    }
+    let m2g = m2 / &g;
+    let lcm = m1 * &m2g;
+    if m2g.is_one() {
+        return Some((a1 % &lcm, lcm));
+    }
+    let t = (&diff / &g) * mod_inverse(&(m1 / &g), &m2g)? % &m2g;
+    Some(((a1 + m1 * t) % &lcm, lcm))
+}
+
+/// The candidates `first + k * step` for `k < count`, minus excluded residues.
+#[derive(Clone, Debug)]
+pub(crate) struct Space {
+    first: BigUint,
+    step: BigUint,
+    count: BigUint,
+    excluded: Vec<(u64, u64)>,
+    digits: usize,
+    /// How far a walk along the progression may go without finding an
+    /// admitted point before none can exist: two periods of the exclusions,
+    /// or the whole progression.
+    span: BigUint,
+}
+
+impl Space {
+    /// Approximate size of the candidates in decimal digits.
+    pub(crate) fn digits(&self) -> usize {
+        self.digits
+    }
+
+    /// Number of points in the progression.
+    pub(crate) fn count(&self) -> &BigUint {
+        &self.count
+    }
+
+    /// How many fruitless draws or steps a search may make in a row before
+    /// concluding the space has nothing (more) to offer: enough to visit
+    /// every point of a small space many times over.
+    pub(crate) fn attempts(&self) -> u64 {
+        self.count.to_u64().map_or(u64::MAX, |count| count.saturating_mul(32).saturating_add(64))
+    }
+
+    /// Distance between consecutive points.
+    pub(crate) fn step(&self) -> &BigUint {
+        &self.step
+    }
+
+    /// The `k`-th point of the progression.
+    pub(crate) fn nth(&self, k: &BigUint) -> BigUint {
+        &self.first + k * &self.step
+    }
+
+    /// Draws a random point of the progression and steps forward (wrapping
+    /// around) to the first one not excluded, checking `stop` on each step.
+    pub(crate) fn sample<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        stop: &Stop,
+    ) -> Result<BigUint, PrimeError> {
+        let mut k = rng.gen_biguint_below(&self.count);
+        let mut candidate = self.nth(&k);
+        let mut remaining = self.span.clone();
+        while !remaining.is_zero() {
+            stop.check()?;
+            if self.admits(&candidate) {
+                return Ok(candidate);
+            }
+            k += 1u32;
+            candidate += &self.step;
+            if k == self.count {
+                k.set_zero();
+                candidate = self.first.clone();
+            }
+            remaining -= 1u32;
+        }
+        Err(PrimeError::Unsatisfiable)
+    }
+
+    /// Returns `false` if `candidate` is in an excluded residue class.
+    pub(crate) fn admits(&self, candidate: &BigUint) -> bool {
+        self.excluded
+            .iter()
+            .all(|&(r, m)| (candidate % m).to_u64() != Some(r % m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::CancellationToken;
+
+    #[test]
+    fn fully_excluded_progressions_are_unsatisfiable() {
+        let size = Size::Digits(30);
+        let covered = Constraints::new().residue(1u32, 4u32).exclude(1, 4);
+        assert_eq!(covered.resolve(size).unwrap_err(), PrimeError::Unsatisfiable);
+
+        let every_class = (0..3).fold(Constraints::new(), |c, r| c.exclude(r, 3));
+        assert_eq!(every_class.resolve(size).unwrap_err(), PrimeError::Unsatisfiable);
+
+        // Odd candidates are 1 or 3 mod 4, so excluding 1 mod 4 and 3 mod 8
+        // and 7 mod 8 leaves nothing.
+        let split = Constraints::new().exclude(1, 4).exclude(3, 8).exclude(7, 8);
+        assert_eq!(split.resolve(size).unwrap_err(), PrimeError::Unsatisfiable);
+    }
+
+    #[test]
+    fn ranges_narrow_the_size() {
+        let size = Size::Bits { bits: 64, top_bits: 2 };
+        let lo = BigUint::from(3u32) << 62;
+        let count = |range: Constraints| range.resolve(size).map(|space| space.count().clone());
+
+        let wide = Constraints::new().range(0u32, BigUint::one() << 65);
+        assert_eq!(count(wide), Ok(BigUint::one() << 61));
+        let straddling = Constraints::new().range(BigUint::one() << 63, &lo + 100u32);
+        assert_eq!(count(straddling), Ok(BigUint::from(50u32)));
+        let inside = Constraints::new().range(&lo + 10u32, &lo + 20u32);
+        assert_eq!(count(inside), Ok(BigUint::from(5u32)));
+        let below = Constraints::new().range(0u32, lo);
+        assert_eq!(count(below), Err(PrimeError::Unsatisfiable));
+    }
+
+    #[test]
+    fn partially_excluded_progressions_sample_admitted_points() {
+        let space = Constraints::new()
+            .residue(3u32, 4u32)
+            .exclude(1, 5)
+            .exclude(2, 5)
+            .exclude(3, 5)
+            .resolve(Size::Digits(30))
+            .unwrap();
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let p = space.sample(&mut rng, &Stop::default()).unwrap();
+            assert_eq!(&p % 4u32, BigUint::from(3u32));
+            assert!([0u32, 4].contains(&(&p % 5u32).to_u32().unwrap()));
+        }
+    }
+
+    #[test]
+    fn sampling_stops_when_cancelled() {
+        let space = Constraints::new().resolve(Size::Digits(30)).unwrap();
+        let token = CancellationToken::new();
+        token.cancel();
+        let stop = Stop::new(Some(token), None);
+        assert_eq!(space.sample(&mut rand::thread_rng(), &stop), Err(PrimeError::Cancelled));
+    }
+}
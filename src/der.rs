@@ -0,0 +1,150 @@
+//! Just enough DER and PEM encoding for the key and parameter formats this
+//! crate writes.
+
+use num_bigint::BigUint;
+
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+
+/// A tag-length-value triple.
+fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = contents.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+/// A non-negative INTEGER, with a leading zero byte if the top bit is set.
+pub fn integer(n: &BigUint) -> Vec<u8> {
+    let mut bytes = n.to_bytes_be();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    tlv(INTEGER, &bytes)
+}
+
+/// A SEQUENCE of already encoded elements.
+pub fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &elements.concat())
+}
+
+pub fn octet_string(contents: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, contents)
+}
+
+/// A BIT STRING with no unused bits.
+pub fn bit_string(contents: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0];
+    bytes.extend_from_slice(contents);
+    tlv(BIT_STRING, &bytes)
+}
+
+pub fn null() -> Vec<u8> {
+    tlv(NULL, &[])
+}
+
+/// An OBJECT IDENTIFIER from its arcs, e.g. `&[1, 2, 840, 113549, 1, 1, 1]`.
+pub fn oid(arcs: &[u64]) -> Vec<u8> {
+    let mut bytes = vec![(arcs[0] * 40 + arcs[1]) as u8];
+    for &arc in &arcs[2..] {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        bytes.extend(groups.into_iter().rev());
+    }
+    tlv(OBJECT_IDENTIFIER, &bytes)
+}
+
+/// PEM armour: base64 in 64-column lines between `BEGIN`/`END` labels.
+pub fn pem(label: &str, der: &[u8]) -> String {
+    let encoded = base64(der);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(std::str::from_utf8(line).unwrap());
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_are_minimal_and_non_negative() {
+        for (n, encoded) in [
+            (0u32, &[0x02, 0x01, 0x00][..]),
+            (0x7f, &[0x02, 0x01, 0x7f][..]),
+            (0x80, &[0x02, 0x02, 0x00, 0x80][..]),
+            (0xff00, &[0x02, 0x03, 0x00, 0xff, 0x00][..]),
+            (0x7fff, &[0x02, 0x02, 0x7f, 0xff][..]),
+        ] {
+            assert_eq!(integer(&BigUint::from(n)), encoded, "{:#x}", n);
+        }
+    }
+
+    #[test]
+    fn lengths_switch_to_long_form_at_128() {
+        for (len, header) in [
+            (0, &[0x04, 0x00][..]),
+            (127, &[0x04, 0x7f][..]),
+            (128, &[0x04, 0x81, 0x80][..]),
+            (255, &[0x04, 0x81, 0xff][..]),
+            (256, &[0x04, 0x82, 0x01, 0x00][..]),
+            (65536, &[0x04, 0x83, 0x01, 0x00, 0x00][..]),
+        ] {
+            let encoded = octet_string(&vec![0xaa; len]);
+            assert_eq!(&encoded[..header.len()], header, "{}", len);
+            assert_eq!(encoded.len(), header.len() + len);
+        }
+    }
+
+    #[test]
+    fn pem_wraps_base64_at_64_columns() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+
+        // 48 bytes fill exactly one line; one more starts a second.
+        let full = pem("TEST", &[0; 48]);
+        assert_eq!(full, format!("-----BEGIN TEST-----\n{}\n-----END TEST-----\n", "A".repeat(64)));
+        let spill = pem("TEST", &[0; 49]);
+        let lines: Vec<&str> = spill.lines().collect();
+        assert_eq!(lines[1..3], ["A".repeat(64), "AA==".to_string()]);
+        assert_eq!(lines.len(), 4);
+    }
+}
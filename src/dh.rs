@@ -0,0 +1,431 @@
+//! Diffie–Hellman and DSA domain parameters `(p, q, g)`, where `q` is a prime
+//! dividing `p - 1` and `g` generates a subgroup of order `q` modulo `p`.
+//!
+//! Three constructions are offered:
+//!
+//! - [`DomainParameters::generate`]: a random `N`-bit `q`, then a random
+//!   `L`-bit `p = 1 (mod 2q)`, both from [`PrimeGenerator`].
+//! - [`DomainParameters::generate_fips186_4`]: FIPS 186-4, Appendix A.1.1.2,
+//!   where `p` and `q` are derived from a hashed seed. The seed and counter
+//!   are kept so anyone can redo the derivation
+//!   ([`verify_seed`](DomainParameters::verify_seed)).
+//! - [`DomainParameters::generate_safe`]: a safe prime `p = 2q + 1` with a
+//!   small generator of the order picked by [`GeneratorOrder`].
+//!
+//! ```no_run
+//! use large_primes::{Size, VerificationPolicy};
+//! use large_primes::dh::{DomainParameters, GeneratorOrder};
+//!
+//! let group = DomainParameters::generate_safe(
+//!     Size::Bits { bits: 2048, top_bits: 1 },
+//!     GeneratorOrder::Subgroup,
+//!     VerificationPolicy::Bpsw,
+//! )?;
+//! std::fs::write("dhparams.pem", group.to_pkcs3_pem()).unwrap();
+//! # Ok::<(), large_primes::PrimeError>(())
+//! ```
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use rand::{CryptoRng, RngCore};
+use rand::rngs::OsRng;
+
+use crate::candidate::Size;
+use crate::constraint::Constraints;
+use crate::der;
+use crate::error::PrimeError;
+use crate::generator::{PrimeGenerator, VerificationPolicy};
+use crate::primality::{bpsw_check, miller_rabin_check_with_rng};
+use crate::sha256::sha256;
+use crate::sieve::sieve_check;
+
+/// The `(L, N)` pairs FIPS 186-4 allows, with the Miller–Rabin rounds its
+/// Table C.1 asks for on `p` and `q`.
+const FIPS186_4_SIZES: [(usize, usize, usize, usize); 4] =
+    [(1024, 160, 40, 40), (2048, 224, 56, 56), (2048, 256, 56, 64), (3072, 256, 64, 64)];
+
+/// Output length of the hash, in bits.
+const OUTLEN: usize = 256;
+
+/// The seed and counter that reproduce FIPS 186-4 parameters, as in X9.42's
+/// `ValidationParms`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationParams {
+    pub seed: Vec<u8>,
+    pub counter: u32,
+}
+
+/// Which subgroup a safe-prime group's generator spans.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GeneratorOrder {
+    /// Order `q = (p - 1) / 2`: the quadratic residues, so `g` leaks nothing
+    /// through the Legendre symbol.
+    #[default]
+    Subgroup,
+    /// Order `p - 1`: a primitive root, as in the classic PKCS#3 groups.
+    Full,
+}
+
+/// Domain parameters `(p, q, g)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainParameters {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    /// Present for parameters from [`generate_fips186_4`](Self::generate_fips186_4).
+    pub validation: Option<ValidationParams>,
+}
+
+impl DomainParameters {
+    /// Random parameters with an `l`-bit `p` and an `n`-bit `q`, both checked
+    /// with `verification`.
+    ///
+    /// `l` must exceed `n` by at least two bits.
+    pub fn generate(l: usize, n: usize, verification: VerificationPolicy) -> Result<Self, PrimeError> {
+        Self::generate_with_rng(&mut OsRng, l, n, verification)
+    }
+
+    /// Like [`generate`](Self::generate), drawing from `rng` instead of
+    /// [`OsRng`].
+    pub fn generate_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        rng: &mut R,
+        l: usize,
+        n: usize,
+        verification: VerificationPolicy,
+    ) -> Result<Self, PrimeError> {
+        if n < 2 || l < n + 2 {
+            return Err(PrimeError::InvalidParameter(format!(
+                "cannot fit a {}-bit q into a {}-bit p",
+                n, l
+            )));
+        }
+        let q = PrimeGenerator::new()
+            .rng(&mut *rng)
+            .bits(n)
+            .verification(verification)
+            .generate()?;
+        let p = PrimeGenerator::new()
+            .rng(rng)
+            .bits(l)
+            .constraints(Constraints::new().residue(1u32, &q << 1))
+            .verification(verification)
+            .generate()?;
+        let g = subgroup_generator(&p, &q);
+        Ok(DomainParameters { p, q, g, validation: None })
+    }
+
+    /// Parameters from FIPS 186-4, Appendix A.1.1.2 with SHA-256 and an
+    /// `n`-bit random seed. `g` follows Appendix A.2.1 with `h = 2, 3, ...`.
+    ///
+    /// `(l, n)` must be one of `(1024, 160)`, `(2048, 224)`, `(2048, 256)`
+    /// or `(3072, 256)`.
+    pub fn generate_fips186_4(l: usize, n: usize) -> Result<Self, PrimeError> {
+        Self::generate_fips186_4_with_rng(&mut OsRng, l, n)
+    }
+
+    /// Like [`generate_fips186_4`](Self::generate_fips186_4), drawing seeds
+    /// and Miller–Rabin bases from `rng` instead of [`OsRng`].
+    pub fn generate_fips186_4_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        rng: &mut R,
+        l: usize,
+        n: usize,
+    ) -> Result<Self, PrimeError> {
+        let rounds = fips186_4_rounds(l, n)?;
+        let mut seed = vec![0u8; n / 8];
+        loop {
+            rng.fill_bytes(&mut seed);
+            if let Some((p, q, counter)) = fips186_4_from_seed(rng, l, n, &seed, rounds) {
+                let g = subgroup_generator(&p, &q);
+                let validation = Some(ValidationParams { seed, counter });
+                return Ok(DomainParameters { p, q, g, validation });
+            }
+        }
+    }
+
+    /// A safe prime `p = 2q + 1` of `size`, checked with `verification`, and
+    /// the smallest generator `g >= 2` of the requested order.
+    pub fn generate_safe(
+        size: Size,
+        order: GeneratorOrder,
+        verification: VerificationPolicy,
+    ) -> Result<Self, PrimeError> {
+        Self::generate_safe_with_rng(&mut OsRng, size, order, verification)
+    }
+
+    /// Like [`generate_safe`](Self::generate_safe), drawing from `rng`
+    /// instead of [`OsRng`].
+    pub fn generate_safe_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        rng: &mut R,
+        size: Size,
+        order: GeneratorOrder,
+        verification: VerificationPolicy,
+    ) -> Result<Self, PrimeError> {
+        let p = PrimeGenerator::new()
+            .rng(rng)
+            .size_of(size)
+            .verification(verification)
+            .generate_safe()?;
+        let q = &p >> 1;
+        let minus_one = &p - 1u32;
+        let mut g = BigUint::from(2u32);
+        loop {
+            let power = g.modpow(&q, &p);
+            let found = match order {
+                GeneratorOrder::Subgroup => power.is_one(),
+                GeneratorOrder::Full => power == minus_one,
+            };
+            if found {
+                return Ok(DomainParameters { p, q, g, validation: None });
+            }
+            g += 1u32;
+        }
+    }
+
+    /// Checks that `p` and `q` are probable primes (BPSW) with `q | p - 1`,
+    /// that `g` has order `q` (or `2q` when `p = 2q + 1`), and, if a seed is
+    /// present, that it reproduces `p` and `q`.
+    pub fn validate(&self) -> Result<(), PrimeError> {
+        let invalid = |reason: &str| Err(PrimeError::InvalidParameter(reason.to_string()));
+        if !bpsw_check(&self.p) || !bpsw_check(&self.q) {
+            return invalid("p and q must be primes");
+        }
+        let minus_one = &self.p - 1u32;
+        if !(&minus_one % &self.q).is_zero() {
+            return invalid("q does not divide p - 1");
+        }
+        if self.g <= BigUint::one() || self.g >= minus_one {
+            return invalid("g must lie in [2, p - 2]");
+        }
+        let power = self.g.modpow(&self.q, &self.p);
+        let full_order = power == minus_one && minus_one == &self.q << 1;
+        if !power.is_one() && !full_order {
+            return invalid("g does not generate the order-q subgroup");
+        }
+        if self.validation.is_some() {
+            self.verify_seed()?;
+        }
+        Ok(())
+    }
+
+    /// Redoes the FIPS 186-4 derivation from the stored seed (Appendix
+    /// A.1.1.3) and checks that it yields `p`, `q` and the stored counter.
+    pub fn verify_seed(&self) -> Result<(), PrimeError> {
+        let Some(validation) = &self.validation else {
+            return Err(PrimeError::InvalidParameter("no seed to verify".to_string()));
+        };
+        let (l, n) = (self.p.bits() as usize, self.q.bits() as usize);
+        let (p_rounds, q_rounds) = fips186_4_rounds(l, n)?;
+        if validation.seed.len() * 8 < n {
+            return Err(PrimeError::InvalidParameter("seed is shorter than q".to_string()));
+        }
+        match fips186_4_from_seed(&mut OsRng, l, n, &validation.seed, (p_rounds, q_rounds)) {
+            Some((p, q, counter))
+                if p == self.p && q == self.q && counter == validation.counter =>
+            {
+                Ok(())
+            }
+            _ => Err(PrimeError::InvalidParameter(
+                "seed and counter do not reproduce p and q".to_string(),
+            )),
+        }
+    }
+
+    /// PKCS#3 `DHParameter ::= SEQUENCE { prime, base }`, DER-encoded.
+    pub fn to_pkcs3_der(&self) -> Vec<u8> {
+        der::sequence(&[der::integer(&self.p), der::integer(&self.g)])
+    }
+
+    /// PKCS#3 `DHParameter` in a `DH PARAMETERS` PEM block.
+    pub fn to_pkcs3_pem(&self) -> String {
+        der::pem("DH PARAMETERS", &self.to_pkcs3_der())
+    }
+
+    /// X9.42 `DomainParameters` (RFC 3279, 2.3.3), with `validationParms`
+    /// when a seed is present, DER-encoded.
+    pub fn to_x942_der(&self) -> Vec<u8> {
+        let mut fields = vec![der::integer(&self.p), der::integer(&self.g), der::integer(&self.q)];
+        if let Some(validation) = &self.validation {
+            fields.push(der::sequence(&[
+                der::bit_string(&validation.seed),
+                der::integer(&BigUint::from(validation.counter)),
+            ]));
+        }
+        der::sequence(&fields)
+    }
+
+    /// X9.42 `DomainParameters` in an `X9.42 DH PARAMETERS` PEM block.
+    pub fn to_x942_pem(&self) -> String {
+        der::pem("X9.42 DH PARAMETERS", &self.to_x942_der())
+    }
+
+    /// `Dss-Parms ::= SEQUENCE { p, q, g }` (RFC 3279, 2.3.2), DER-encoded.
+    pub fn to_dsa_der(&self) -> Vec<u8> {
+        der::sequence(&[der::integer(&self.p), der::integer(&self.q), der::integer(&self.g)])
+    }
+
+    /// `Dss-Parms` in a `DSA PARAMETERS` PEM block.
+    pub fn to_dsa_pem(&self) -> String {
+        der::pem("DSA PARAMETERS", &self.to_dsa_der())
+    }
+}
+
+/// Table C.1's Miller–Rabin rounds for `(p, q)`, if `(l, n)` is allowed.
+fn fips186_4_rounds(l: usize, n: usize) -> Result<(usize, usize), PrimeError> {
+    FIPS186_4_SIZES
+        .iter()
+        .find(|&&(l2, n2, _, _)| (l2, n2) == (l, n))
+        .map(|&(_, _, p_rounds, q_rounds)| (p_rounds, q_rounds))
+        .ok_or_else(|| {
+            PrimeError::InvalidParameter(format!("(L, N) = ({}, {}) is not a FIPS 186-4 size", l, n))
+        })
+}
+
+/// Steps 6–11 of Appendix A.1.1.2 for one seed: `Some((p, q, counter))`, or
+/// `None` if the seed gives a composite `q` or no `p` within `4L` tries.
+/// Miller–Rabin bases come from `rng`.
+fn fips186_4_from_seed<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    l: usize,
+    n: usize,
+    seed: &[u8],
+    (p_rounds, q_rounds): (usize, usize),
+) -> Option<(BigUint, BigUint, u32)> {
+    let seedlen = seed.len() * 8;
+    let seed_value = BigUint::from_bytes_be(seed);
+    let hash = |x: &BigUint| {
+        let bytes = (x % (BigUint::one() << seedlen)).to_bytes_be();
+        let mut padded = vec![0u8; seed.len() - bytes.len().min(seed.len())];
+        padded.extend(bytes);
+        BigUint::from_bytes_be(&sha256(&padded))
+    };
+
+    let top = BigUint::one() << (n - 1);
+    let u = hash(&seed_value) % &top;
+    let q = &top + &u + 1u32 - (&u & BigUint::one());
+    if !sieve_check(&q) || !miller_rabin_check_with_rng(rng, &q, q_rounds) {
+        return None;
+    }
+
+    let blocks = l.div_ceil(OUTLEN) - 1;
+    let b = l - 1 - blocks * OUTLEN;
+    let two_q = &q << 1;
+    let low = BigUint::one() << (l - 1);
+    let mut offset = 1usize;
+    for counter in 0..4 * l as u32 {
+        let mut w = BigUint::zero();
+        for j in 0..=blocks {
+            let mut v = hash(&(&seed_value + offset + j));
+            if j == blocks {
+                v %= BigUint::one() << b;
+            }
+            w += v << (j * OUTLEN);
+        }
+        let x = w + &low;
+        let c = &x % &two_q;
+        let p = x + 1u32 - c;
+        if p >= low && sieve_check(&p) && miller_rabin_check_with_rng(rng, &p, p_rounds) {
+            return Some((p, q, counter));
+        }
+        offset += blocks + 1;
+    }
+    None
+}
+
+/// Appendix A.2.1: `g = h^((p - 1) / q) mod p` for the first `h = 2, 3, ...`
+/// that does not give 1.
+fn subgroup_generator(p: &BigUint, q: &BigUint) -> BigUint {
+    let e = (p - 1u32) / q;
+    let mut h = BigUint::from(2u32);
+    loop {
+        let g = h.modpow(&e, p);
+        if !g.is_one() {
+            return g;
+        }
+        h += 1u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    #[test]
+    fn generate_fips186_4_with_rng_is_reproducible() {
+        let params = || {
+            let rng = &mut ChaCha20Rng::seed_from_u64(3);
+            DomainParameters::generate_fips186_4_with_rng(rng, 1024, 160).unwrap()
+        };
+        let first = params();
+        first.validate().unwrap();
+        first.verify_seed().unwrap();
+        assert_eq!(params(), first);
+    }
+
+    /// `openssl genpkey -genparam -algorithm DHX -pkeyopt type:fips186_4
+    /// -pkeyopt pbits:1024 -pkeyopt qbits:160 -pkeyopt digest:SHA256`, with
+    /// its seed and counter read back through `openssl pkeyparam -text`.
+    fn openssl_params() -> DomainParameters {
+        let hex = |digits: &str| BigUint::parse_bytes(digits.as_bytes(), 16).unwrap();
+        let p = hex(concat!(
+            "d2163368e073528ec0bff7481539357ef1d3d0b2ccca1ef4674bf897e9660ef8",
+            "192d444e865451ba1410863f9e956ae892f23d1f00f874127ab537e8d77b5d09",
+            "c487ab2910a71c7f90fbed8250058550fcccf3c3f4e3627ebf25fbc3842d50b4",
+            "b10010a867aa2c0fbaa7f7b41aca105fdbefe0e80c5d0eaa96a4ba127fd19bd1",
+        ));
+        let q = hex("e3f834f6623268e45900373256aab4242443cf73");
+        let g = hex(concat!(
+            "2934fc5de34e8d22cf81840c08d181342c0cf8c08ac0d60ef1bfe165856b080c",
+            "117695fb18d7fb8dbeae763932e99ae097595a648af64fc24fce3cb11927703e",
+            "833d729405050d337e72024e40248cf28a0dc76ea7b912d66819e597e6bfc972",
+            "b08640a1bf42b81494bc42d33c22a1e32f0a7eadcb0b24a1f302f32eba8af31b",
+        ));
+        let seed = "6fcb5f9c72c66b41142926676ad03fcb259cef0bcc565f9b9d8191b306699b1c";
+        let seed = hex(seed).to_bytes_be();
+        DomainParameters { p, q, g, validation: Some(ValidationParams { seed, counter: 18 }) }
+    }
+
+    #[test]
+    fn fips186_4_seed_reproduces_openssl_parameters() {
+        let expected = openssl_params();
+        let validation = expected.validation.clone().unwrap();
+        let rng = &mut ChaCha20Rng::seed_from_u64(0);
+        let rounds = fips186_4_rounds(1024, 160).unwrap();
+        let derived = fips186_4_from_seed(rng, 1024, 160, &validation.seed, rounds);
+        assert_eq!(derived, Some((expected.p.clone(), expected.q.clone(), validation.counter)));
+        expected.validate().unwrap();
+    }
+
+    #[test]
+    fn encodings_match_openssl() {
+        let params = openssl_params();
+        // As written by `openssl genpkey` above.
+        let x942 = concat!(
+            "-----BEGIN X9.42 DH PARAMETERS-----\n",
+            "MIIBRgKBgQDSFjNo4HNSjsC/90gVOTV+8dPQsszKHvRnS/iX6WYO+BktRE6GVFG6\n",
+            "FBCGP56VauiS8j0fAPh0Enq1N+jXe10JxIerKRCnHH+Q++2CUAWFUPzM88P042J+\n",
+            "vyX7w4QtULSxABCoZ6osD7qn97QayhBf2+/g6AxdDqqWpLoSf9Gb0QKBgCk0/F3j\n",
+            "To0iz4GEDAjRgTQsDPjAisDWDvG/4WWFawgMEXaV+xjX+42+rnY5Muma4JdZWmSK\n",
+            "9k/CT848sRkncD6DPXKUBQUNM35yAk5AJIzyig3Hbqe5EtZoGeWX5r/JcrCGQKG/\n",
+            "QrgUlLxC0zwioeMvCn6tywskofMC8y66ivMbAhUA4/g09mIyaORZADcyVqq0JCRD\n",
+            "z3MwJgMhAG/LX5xyxmtBFCkmZ2rQP8slnO8LzFZfm52BkbMGaZscAgES\n",
+            "-----END X9.42 DH PARAMETERS-----\n",
+        );
+        assert_eq!(params.to_x942_pem(), x942);
+        // `openssl asn1parse -genconf` on `SEQUENCE { INTEGER p, INTEGER g }`,
+        // which `openssl dhparam` reads back.
+        let pkcs3 = concat!(
+            "-----BEGIN DH PARAMETERS-----\n",
+            "MIIBBwKBgQDSFjNo4HNSjsC/90gVOTV+8dPQsszKHvRnS/iX6WYO+BktRE6GVFG6\n",
+            "FBCGP56VauiS8j0fAPh0Enq1N+jXe10JxIerKRCnHH+Q++2CUAWFUPzM88P042J+\n",
+            "vyX7w4QtULSxABCoZ6osD7qn97QayhBf2+/g6AxdDqqWpLoSf9Gb0QKBgCk0/F3j\n",
+            "To0iz4GEDAjRgTQsDPjAisDWDvG/4WWFawgMEXaV+xjX+42+rnY5Muma4JdZWmSK\n",
+            "9k/CT848sRkncD6DPXKUBQUNM35yAk5AJIzyig3Hbqe5EtZoGeWX5r/JcrCGQKG/\n",
+            "QrgUlLxC0zwioeMvCn6tywskofMC8y66ivMb\n",
+            "-----END DH PARAMETERS-----\n",
+        );
+        assert_eq!(params.to_pkcs3_pem(), pkcs3);
+    }
+}
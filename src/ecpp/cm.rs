@@ -0,0 +1,374 @@
+//! Complex multiplication data: imaginary quadratic discriminants, their
+//! reduced forms, and Hilbert class polynomials computed from the floating
+//! point values of `j` at the CM points.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+/// Largest `|D|` considered by the downrun.
+pub const MAX_ABS_DISCRIMINANT: usize = 100_000;
+
+/// Largest class number considered by the downrun.
+pub const MAX_CLASS_NUMBER: usize = 64;
+
+/// A fundamental discriminant `D < 0` together with its class number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Discriminant {
+    pub d: i64,
+    pub class_number: usize,
+}
+
+/// Fundamental discriminants with `|D| <= MAX_ABS_DISCRIMINANT` and class
+/// number at most `MAX_CLASS_NUMBER`, cheapest first.
+pub static DISCRIMINANTS: LazyLock<Vec<Discriminant>> = LazyLock::new(|| {
+    let class_numbers = class_numbers(MAX_ABS_DISCRIMINANT);
+    let mut discriminants: Vec<Discriminant> = (3..=MAX_ABS_DISCRIMINANT)
+        .filter(|&abs_d| is_fundamental(-(abs_d as i64)))
+        .map(|abs_d| Discriminant { d: -(abs_d as i64), class_number: class_numbers[abs_d] })
+        .filter(|disc| disc.class_number <= MAX_CLASS_NUMBER)
+        .collect();
+    discriminants.sort_by_key(|disc| (disc.class_number, -disc.d));
+    discriminants
+});
+
+/// Counts reduced forms for every discriminant `-limit <= D < 0` at once by
+/// enumerating `(a, b, c)` with `|b| <= a <= c`.
+///
+/// Non-primitive forms are included, so the counts are only class numbers for
+/// fundamental discriminants.
+fn class_numbers(limit: usize) -> Vec<usize> {
+    let limit = limit as i64;
+    let mut counts = vec![0usize; limit as usize + 1];
+    let mut a = 1;
+    while 3 * a * a <= limit {
+        for b in -a + 1..=a {
+            let mut c = a;
+            loop {
+                let abs_d = 4 * a * c - b * b;
+                if abs_d > limit {
+                    break;
+                }
+                if !(b < 0 && a == c) {
+                    counts[abs_d as usize] += 1;
+                }
+                c += 1;
+            }
+        }
+        a += 1;
+    }
+    counts
+}
+
+static HILBERT_CACHE: LazyLock<Mutex<HashMap<i64, Arc<Vec<BigInt>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn is_squarefree(mut n: u64) -> bool {
+    let mut p = 2;
+    while p * p <= n {
+        if n.is_multiple_of(p * p) {
+            return false;
+        }
+        if n.is_multiple_of(p) {
+            n /= p;
+        }
+        p += 1;
+    }
+    true
+}
+
+/// Returns `true` if `d < 0` is a fundamental discriminant.
+pub fn is_fundamental(d: i64) -> bool {
+    let abs_d = d.unsigned_abs();
+    match d.rem_euclid(4) {
+        1 => is_squarefree(abs_d),
+        0 => {
+            let m = d / 4;
+            matches!(m.rem_euclid(4), 2 | 3) && is_squarefree(m.unsigned_abs())
+        }
+        _ => false,
+    }
+}
+
+/// Reduced primitive binary quadratic forms `(a, b, c)` of discriminant `d`.
+pub fn reduced_forms(d: i64) -> Vec<(i64, i64, i64)> {
+    let abs_d = -d;
+    let mut forms = Vec::new();
+    let mut a = 1;
+    while 3 * a * a <= abs_d {
+        for b in -a + 1..=a {
+            if (b * b - d) % (4 * a) != 0 {
+                continue;
+            }
+            let c = (b * b - d) / (4 * a);
+            if c < a || (b < 0 && (a == c)) {
+                continue;
+            }
+            if a.gcd(&b).gcd(&c) != 1 {
+                continue;
+            }
+            forms.push((a, b, c));
+        }
+        a += 1;
+    }
+    forms
+}
+
+/// Monic Hilbert class polynomial of `d`, coefficients from the constant term up.
+pub fn hilbert_polynomial(d: i64) -> Arc<Vec<BigInt>> {
+    if let Some(poly) = HILBERT_CACHE.lock().unwrap().get(&d) {
+        return Arc::clone(poly);
+    }
+    let poly = Arc::new(compute_hilbert_polynomial(d));
+    HILBERT_CACHE.lock().unwrap().insert(d, Arc::clone(&poly));
+    poly
+}
+
+fn compute_hilbert_polynomial(d: i64) -> Vec<BigInt> {
+    match d {
+        -3 => return vec![BigInt::zero(), BigInt::one()],
+        -4 => return vec![BigInt::from(-1728), BigInt::one()],
+        _ => {}
+    }
+    let forms = reduced_forms(d);
+    let sqrt_d = (-d as f64).sqrt();
+    let log2_bound: f64 = forms
+        .iter()
+        .map(|&(a, _, _)| std::f64::consts::PI * sqrt_d / a as f64 / std::f64::consts::LN_2 + 1.0)
+        .sum();
+    let mut prec = log2_bound.ceil() as u64 + 2 * forms.len() as u64 + 64;
+
+    loop {
+        if let Some(poly) = try_hilbert_polynomial(d, &forms, prec) {
+            return poly;
+        }
+        prec *= 2;
+    }
+}
+
+fn try_hilbert_polynomial(d: i64, forms: &[(i64, i64, i64)], prec: u64) -> Option<Vec<BigInt>> {
+    let ctx = Fixed::new(prec + 32);
+    let mut coeffs = vec![Complex::from_int(&ctx, 1)];
+    for &(a, b, _) in forms {
+        let j = ctx.j_invariant(d, a, b);
+        // Multiply the running product by (x - j).
+        let mut next = vec![Complex::zero(); coeffs.len() + 1];
+        for (i, c) in coeffs.iter().enumerate() {
+            next[i + 1] = next[i + 1].add(c);
+            next[i] = next[i].sub(&ctx.mul(c, &j));
+        }
+        coeffs = next;
+    }
+
+    let half = BigInt::one() << (ctx.prec - 1);
+    let tolerance = BigInt::one() << (ctx.prec - 8);
+    let mut poly = Vec::with_capacity(coeffs.len());
+    for c in coeffs {
+        if c.im.abs() > tolerance {
+            return None;
+        }
+        let rounded = (&c.re + &half) >> ctx.prec;
+        let error = &c.re - (&rounded << ctx.prec);
+        if error.abs() > tolerance {
+            return None;
+        }
+        poly.push(rounded);
+    }
+    Some(poly)
+}
+
+/// A complex number in fixed-point representation.
+#[derive(Clone, Debug)]
+struct Complex {
+    re: BigInt,
+    im: BigInt,
+}
+
+impl Complex {
+    fn zero() -> Self {
+        Complex { re: BigInt::zero(), im: BigInt::zero() }
+    }
+
+    fn from_int(ctx: &Fixed, n: i64) -> Self {
+        Complex { re: BigInt::from(n) << ctx.prec, im: BigInt::zero() }
+    }
+
+    fn add(&self, other: &Complex) -> Complex {
+        Complex { re: &self.re + &other.re, im: &self.im + &other.im }
+    }
+
+    fn sub(&self, other: &Complex) -> Complex {
+        Complex { re: &self.re - &other.re, im: &self.im - &other.im }
+    }
+
+    fn is_negligible(&self) -> bool {
+        self.re.bits() <= 2 && self.im.bits() <= 2
+    }
+}
+
+/// Fixed-point arithmetic with `prec` fractional bits.
+struct Fixed {
+    prec: u64,
+    pi: BigInt,
+    ln2: BigInt,
+}
+
+impl Fixed {
+    fn new(prec: u64) -> Self {
+        let mut ctx = Fixed { prec, pi: BigInt::zero(), ln2: BigInt::zero() };
+        ctx.pi = ctx.compute_pi();
+        ctx.ln2 = ctx.compute_ln2();
+        ctx
+    }
+
+    fn one(&self) -> BigInt {
+        BigInt::one() << self.prec
+    }
+
+    fn mul_real(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b) >> self.prec
+    }
+
+    fn mul(&self, a: &Complex, b: &Complex) -> Complex {
+        Complex {
+            re: (&a.re * &b.re - &a.im * &b.im) >> self.prec,
+            im: (&a.re * &b.im + &a.im * &b.re) >> self.prec,
+        }
+    }
+
+    fn div(&self, a: &Complex, b: &Complex) -> Complex {
+        let denom = &b.re * &b.re + &b.im * &b.im;
+        Complex {
+            re: ((&a.re * &b.re + &a.im * &b.im) << self.prec) / &denom,
+            im: ((&a.im * &b.re - &a.re * &b.im) << self.prec) / &denom,
+        }
+    }
+
+    /// `atan(1/x)` for an integer `x > 1`.
+    fn atan_inv(&self, x: u32) -> BigInt {
+        let x = BigInt::from(x);
+        let x2 = &x * &x;
+        let mut power = self.one() / &x;
+        let mut sum = BigInt::zero();
+        let mut k = 0u32;
+        while !power.is_zero() {
+            let term = &power / BigInt::from(2 * k + 1);
+            if k.is_multiple_of(2) { sum += term } else { sum -= term }
+            power /= &x2;
+            k += 1;
+        }
+        sum
+    }
+
+    fn compute_pi(&self) -> BigInt {
+        // Machin: pi = 16 atan(1/5) - 4 atan(1/239).
+        self.atan_inv(5) * 16 - self.atan_inv(239) * 4
+    }
+
+    fn compute_ln2(&self) -> BigInt {
+        // ln 2 = sum_{k >= 1} 1 / (k 2^k).
+        let mut sum = BigInt::zero();
+        let mut power: BigInt = self.one() >> 1;
+        let mut k = 1u32;
+        while !power.is_zero() {
+            sum += &power / BigInt::from(k);
+            power >>= 1;
+            k += 1;
+        }
+        sum
+    }
+
+    fn exp_real(&self, x: &BigInt) -> BigInt {
+        // exp(x) = 2^n exp(r) with |r| <= ln 2 / 2.
+        let n = (x + (&self.ln2 >> 1u32)).div_floor(&self.ln2);
+        let r = x - &n * &self.ln2;
+        let mut sum = self.one();
+        let mut term = self.one();
+        let mut k = 1u32;
+        loop {
+            term = self.mul_real(&term, &r) / BigInt::from(k);
+            if term.is_zero() {
+                break;
+            }
+            sum += &term;
+            k += 1;
+        }
+        let n: i64 = n.try_into().expect("exponent out of range");
+        if n >= 0 { sum << n as u64 } else { sum >> (-n) as u64 }
+    }
+
+    /// `(cos x, sin x)` for `|x| <= pi`.
+    fn cos_sin(&self, x: &BigInt) -> (BigInt, BigInt) {
+        let x2 = self.mul_real(x, x);
+        let mut cos = self.one();
+        let mut sin = x.clone();
+        let mut cos_term = self.one();
+        let mut sin_term = x.clone();
+        let mut k = 1u32;
+        while !cos_term.is_zero() || !sin_term.is_zero() {
+            cos_term = -self.mul_real(&cos_term, &x2) / BigInt::from((2 * k - 1) * (2 * k));
+            sin_term = -self.mul_real(&sin_term, &x2) / BigInt::from((2 * k) * (2 * k + 1));
+            cos += &cos_term;
+            sin += &sin_term;
+            k += 1;
+        }
+        (cos, sin)
+    }
+
+    /// `prod_{n >= 1} (1 - q^n)` via the pentagonal number theorem.
+    fn euler_product(&self, q: &Complex) -> Complex {
+        let q2 = self.mul(q, q);
+        let q3 = self.mul(&q2, q);
+        let mut sum = Complex::from_int(self, 1);
+        // q^k, q^{k(3k-1)/2} and q^{3k+1} for the current k.
+        let mut qk = q.clone();
+        let mut pent = q.clone();
+        let mut step = self.mul(&q3, q);
+        let mut k = 1u32;
+        loop {
+            let pair = pent.add(&self.mul(&pent, &qk));
+            if pair.is_negligible() {
+                break;
+            }
+            sum = if k % 2 == 1 { sum.sub(&pair) } else { sum.add(&pair) };
+            pent = self.mul(&pent, &step);
+            step = self.mul(&step, &q3);
+            qk = self.mul(&qk, q);
+            k += 1;
+        }
+        sum
+    }
+
+    /// `j((-b + sqrt(d)) / 2a)`.
+    fn j_invariant(&self, d: i64, a: i64, b: i64) -> Complex {
+        let sqrt_abs_d = BigInt::from((BigUint::from(d.unsigned_abs()) << (2 * self.prec)).sqrt());
+        let y = self.mul_real(&self.pi, &sqrt_abs_d) / BigInt::from(a);
+
+        // Angle pi * b / a reduced into (-pi, pi].
+        let b_red = (b + a).rem_euclid(2 * a) - a;
+        let theta = &self.pi * BigInt::from(b_red) / BigInt::from(a);
+        let (cos, sin) = self.cos_sin(&theta);
+
+        // q = exp(-y) e^{-i theta}, 1/q = exp(y) e^{i theta}.
+        let small = self.exp_real(&-&y);
+        let large = self.exp_real(&y);
+        let q = Complex { re: self.mul_real(&small, &cos), im: -self.mul_real(&small, &sin) };
+        let q_inv = Complex { re: self.mul_real(&large, &cos), im: self.mul_real(&large, &sin) };
+
+        let ratio = self.div(&self.euler_product(&q), &self.euler_product(&self.mul(&q, &q)));
+        let mut ratio8 = ratio;
+        for _ in 0..3 {
+            ratio8 = self.mul(&ratio8, &ratio8);
+        }
+        let ratio24 = self.mul(&self.mul(&ratio8, &ratio8), &ratio8);
+
+        // t = 1/h where h = Delta(2 tau) / Delta(tau); j = (t + 256)^3 / t^2.
+        let t = self.mul(&q_inv, &ratio24);
+        let t256 = t.add(&Complex::from_int(self, 256));
+        let num = self.mul(&self.mul(&t256, &t256), &t256);
+        self.div(&self.div(&num, &t), &t)
+    }
+}
@@ -0,0 +1,144 @@
+//! Short Weierstrass curves `y^2 = x^3 + ax + b` over `Z/nZ`.
+//!
+//! Arithmetic uses Jacobian coordinates. The point at infinity is only ever
+//! produced by an exact cancellation modulo `n`; every other intermediate `Z`
+//! coordinate is checked for invertibility, so a successful computation
+//! reduces to the correct group law modulo every prime factor of `n`. This is
+//! what makes the results usable in a primality proof when `n` is not yet
+//! known to be prime.
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::arith::{mod_inverse, mod_sub};
+
+/// An affine point on a curve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Point {
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+/// Returned when `n` is exposed as composite during a computation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotInvertible;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Curve {
+    pub a: BigUint,
+    pub b: BigUint,
+    pub n: BigUint,
+}
+
+#[derive(Clone)]
+struct Jacobian {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+impl Curve {
+    pub fn new(a: BigUint, b: BigUint, n: BigUint) -> Self {
+        Curve { a: a % &n, b: b % &n, n }
+    }
+
+    /// Returns `true` if `4a^3 + 27b^2` is invertible modulo `n`.
+    pub fn is_nonsingular(&self) -> bool {
+        let n = &self.n;
+        let a3 = (&self.a * &self.a % n) * &self.a % n;
+        let b2 = &self.b * &self.b % n;
+        let disc = (a3 * 4u32 + b2 * 27u32) % n;
+        mod_inverse(&disc, n).is_some()
+    }
+
+    /// `x^3 + ax + b`.
+    pub fn rhs(&self, x: &BigUint) -> BigUint {
+        let n = &self.n;
+        let x2 = x * x % n;
+        (x2 * x + &self.a * x + &self.b) % n
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        p.x < self.n && p.y < self.n && (&p.y * &p.y) % &self.n == self.rhs(&p.x)
+    }
+
+    /// Computes `k * p`, returning `Ok(None)` for the point at infinity.
+    pub fn mul(&self, p: &Point, k: &BigUint) -> Result<Option<Point>, NotInvertible> {
+        let n = &self.n;
+        let mut acc: Option<Jacobian> = None;
+        let mut z_product = BigUint::one();
+
+        for i in (0..k.bits()).rev() {
+            if let Some(r) = acc {
+                acc = self.double(&r);
+                if let Some(r) = &acc {
+                    z_product = z_product * &r.z % n;
+                }
+            }
+            if k.bit(i) {
+                acc = match acc {
+                    None => Some(Jacobian { x: p.x.clone(), y: p.y.clone(), z: BigUint::one() }),
+                    Some(r) => self.add_affine(&r, p)?,
+                };
+                if let Some(r) = &acc {
+                    z_product = z_product * &r.z % n;
+                }
+            }
+        }
+
+        if mod_inverse(&z_product, n).is_none() {
+            return Err(NotInvertible);
+        }
+        match acc {
+            None => Ok(None),
+            Some(r) => {
+                let z_inv = mod_inverse(&r.z, n).ok_or(NotInvertible)?;
+                let z_inv2 = &z_inv * &z_inv % n;
+                let z_inv3 = &z_inv2 * &z_inv % n;
+                Ok(Some(Point { x: r.x * z_inv2 % n, y: r.y * z_inv3 % n }))
+            }
+        }
+    }
+
+    fn double(&self, p: &Jacobian) -> Option<Jacobian> {
+        let n = &self.n;
+        if p.y.is_zero() {
+            return None;
+        }
+        let y2 = &p.y * &p.y % n;
+        let s = (&p.x * &y2 % n) * 4u32 % n;
+        let z2 = &p.z * &p.z % n;
+        let z4 = &z2 * &z2 % n;
+        let m = (&p.x * &p.x * 3u32 + &self.a * z4) % n;
+        let x3 = mod_sub(&(&m * &m % n), &(&s * 2u32 % n), n);
+        let y4 = &y2 * &y2 % n;
+        let y3 = mod_sub(&(m * mod_sub(&s, &x3, n) % n), &(y4 * 8u32 % n), n);
+        let z3 = (&p.y * &p.z * 2u32) % n;
+        Some(Jacobian { x: x3, y: y3, z: z3 })
+    }
+
+    fn add_affine(&self, p: &Jacobian, q: &Point) -> Result<Option<Jacobian>, NotInvertible> {
+        let n = &self.n;
+        let z2 = &p.z * &p.z % n;
+        let u2 = &q.x * &z2 % n;
+        let s2 = (&q.y * &z2 % n) * &p.z % n;
+        if u2 == p.x {
+            if s2 == p.y {
+                return Ok(self.double(p));
+            }
+            if (&s2 + &p.y) % n == BigUint::zero() {
+                return Ok(None);
+            }
+            return Err(NotInvertible);
+        }
+        let h = mod_sub(&u2, &p.x, n);
+        let r = mod_sub(&s2, &p.y, n);
+        let h2 = &h * &h % n;
+        let h3 = &h2 * &h % n;
+        let x1h2 = &p.x * &h2 % n;
+        let x3 = mod_sub(&mod_sub(&(&r * &r % n), &h3, n), &(&x1h2 * 2u32 % n), n);
+        let y3 = mod_sub(&(r * mod_sub(&x1h2, &x3, n) % n), &(&p.y * &h3 % n), n);
+        let z3 = &p.z * &h % n;
+        Ok(Some(Jacobian { x: x3, y: y3, z: z3 }))
+    }
+}
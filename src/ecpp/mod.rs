@@ -0,0 +1,283 @@
+//! Native elliptic curve primality proving (Atkin–Morain ECPP).
+//!
+//! The downrun repeatedly finds a CM discriminant `D` for which the current
+//! number `n` splits as `4n = u^2 + |D| v^2`, picks an elliptic curve over
+//! `Z/nZ` whose order `m` is `k * q` with `q` a probable prime larger than
+//! `(n^(1/4) + 1)^2`, and exhibits a point of order `q`. Proving `q` prime then
+//! proves `n` prime. The chain stops once `n` is small enough for a
+//! deterministic Miller–Rabin test.
+
+pub mod cm;
+pub mod curve;
+mod poly;
+
+use std::sync::LazyLock;
+
+use num_bigint::{BigInt, BigUint, RandBigInt, Sign};
+use num_prime::nt_funcs::is_prime64;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::{Rng, thread_rng};
+use rayon::prelude::*;
+
+use crate::arith::{cornacchia4, mod_inverse, mod_sub, sqrt_mod};
+use crate::cancel::Stop;
+use crate::error::PrimeError;
+use crate::primality::{is_strong_probable_prime, probable_prime_check};
+use cm::DISCRIMINANTS;
+use curve::{Curve, Point};
+
+/// Primes removed from a curve order before testing the cofactor.
+const ORDER_TRIAL_BOUND: u32 = 1 << 16;
+
+/// Curve/point attempts per candidate order before moving on.
+const CURVE_ATTEMPTS: usize = 64;
+
+/// One link of an ECPP certificate chain.
+///
+/// The curve `y^2 = x^3 + ax + b` over `Z/nZ` has order `m = k * q`, and
+/// `point` satisfies `k * point != O` and `q * (k * point) = O`. The CM
+/// discriminant `d` is informational and absent from imported certificates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcppStep {
+    pub n: BigUint,
+    pub d: Option<i64>,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub m: BigUint,
+    pub q: BigUint,
+    pub point: Point,
+}
+
+static ORDER_TRIAL_PRIMES: LazyLock<Vec<u32>> = LazyLock::new(|| {
+    let limit = ORDER_TRIAL_BOUND as usize;
+    let mut composite = vec![false; limit];
+    let mut primes = Vec::new();
+    for i in 2..limit {
+        if !composite[i] {
+            primes.push(i as u32);
+            for j in (i * i..limit).step_by(i) {
+                composite[j] = true;
+            }
+        }
+    }
+    primes
+});
+
+/// Proves `n` prime with ECPP.
+///
+/// Returns `false` if `n` is composite or if no proof was found with the
+/// built-in discriminant table, which does not happen in practice for primes.
+pub fn ecpp_prime_check(n: &BigUint) -> bool {
+    prove(n).is_some()
+}
+
+/// Runs the ECPP downrun for `n`, returning the chain of steps down to a
+/// number that fits in a `u64`, where `is_prime64` is deterministic.
+pub fn prove(n: &BigUint) -> Option<Vec<EcppStep>> {
+    prove_until(n, &Stop::default()).unwrap_or(None)
+}
+
+/// Like [`prove`], abandoning the downrun once `stop` says so.
+pub(crate) fn prove_until(n: &BigUint, stop: &Stop) -> Result<Option<Vec<EcppStep>>, PrimeError> {
+    if n.bits() > 64 && !probable_prime_check(n) {
+        return Ok(None);
+    }
+    let steps = downrun(n, &mut thread_rng(), stop);
+    stop.check()?;
+    Ok(steps)
+}
+
+/// Depth-first downrun: if no proof is found below the chosen `q`, the next
+/// candidate order at this level is tried instead. Returns `None` early once
+/// `stop` says so.
+fn downrun<R: Rng>(n: &BigUint, rng: &mut R, stop: &Stop) -> Option<Vec<EcppStep>> {
+    if let Some(small) = n.to_u64() {
+        return is_prime64(small).then(Vec::new);
+    }
+    let q_min = q_lower_bound(n);
+    let chunk_size = rayon::current_num_threads() * 4;
+
+    for chunk in DISCRIMINANTS.chunks(chunk_size) {
+        stop.check().ok()?;
+        let candidates: Vec<(i64, BigUint, BigUint)> = chunk
+            .par_iter()
+            .flat_map_iter(|disc| {
+                if stop.check().is_err() {
+                    return Vec::new();
+                }
+                candidate_orders(n, disc.d, &q_min)
+            })
+            .collect();
+        for (d, m, q) in candidates {
+            let Some(mut steps) = downrun(&q, rng, stop) else {
+                continue;
+            };
+            if let Some(step) = find_curve(n, d, &m, &q, rng, stop) {
+                steps.insert(0, step);
+                return Some(steps);
+            }
+        }
+    }
+    None
+}
+
+/// Curve orders `m = k * q` for discriminant `d` whose cofactor `q` is a
+/// probable prime small enough to make progress and large enough to prove `n`.
+fn candidate_orders(n: &BigUint, d: i64, q_min: &BigUint) -> Vec<(i64, BigUint, BigUint)> {
+    let Some((u, v)) = cornacchia4(d, n) else {
+        return Vec::new();
+    };
+    let n_int = BigInt::from_biguint(Sign::Plus, n.clone());
+    traces(d, &u, &v)
+        .into_iter()
+        .filter_map(|trace| (&n_int + 1u32 - trace).to_biguint())
+        .filter_map(|m| {
+            let q = remove_small_factors(&m);
+            (&q > q_min && &q < n && is_probable_cofactor(&q)).then_some((d, m, q))
+        })
+        .collect()
+}
+
+/// Returns `(floor(n^(1/4)) + 2)^2`, a bound exceeding `(n^(1/4) + 1)^2`.
+pub fn q_lower_bound(n: &BigUint) -> BigUint {
+    let r = n.nth_root(4) + 2u32;
+    &r * &r
+}
+
+/// Frobenius traces `t` of the curves with CM by `d` modulo `n`, given `4n = u^2 + |d| v^2`.
+fn traces(d: i64, u: &BigUint, v: &BigUint) -> Vec<BigInt> {
+    let u = BigInt::from_biguint(Sign::Plus, u.clone());
+    let v = BigInt::from_biguint(Sign::Plus, v.clone());
+    let mut traces = match d {
+        -4 => vec![u.clone(), &v * 2],
+        -3 => vec![u.clone(), (&u + &v * 3) / 2, (&u - &v * 3) / 2],
+        _ => vec![u.clone()],
+    };
+    let negated: Vec<BigInt> = traces.iter().map(|t| -t).collect();
+    traces.extend(negated);
+    traces
+}
+
+/// Divides out every prime below [`ORDER_TRIAL_BOUND`].
+fn remove_small_factors(m: &BigUint) -> BigUint {
+    let mut q = m.clone();
+    for &p in ORDER_TRIAL_PRIMES.iter() {
+        while !q.is_zero() && (&q % p).is_zero() {
+            q /= p;
+        }
+    }
+    q
+}
+
+fn is_probable_cofactor(q: &BigUint) -> bool {
+    is_strong_probable_prime(q, &BigUint::from(2u32)) && probable_prime_check(q)
+}
+
+/// Finds a curve with CM by `d` and order `m` modulo `n`, and a point on it
+/// whose order is divisible by `q`.
+fn find_curve<R: Rng>(
+    n: &BigUint,
+    d: i64,
+    m: &BigUint,
+    q: &BigUint,
+    rng: &mut R,
+    stop: &Stop,
+) -> Option<EcppStep> {
+    let j = match d {
+        -3 => BigUint::zero(),
+        -4 => BigUint::from(1728u32),
+        _ => poly::find_root(&cm::hilbert_polynomial(d), n, rng, stop)?,
+    };
+    // Any curve with invariant j: k = j / (1728 - j), a = 3k, b = 2k.
+    let base = if d < -4 {
+        let k = (&j * mod_inverse(&mod_sub(&BigUint::from(1728u32), &j, n), n)?) % n;
+        Some(((&k * 3u32) % n, (&k * 2u32) % n))
+    } else {
+        None
+    };
+    let cofactor = m / q;
+
+    for _ in 0..CURVE_ATTEMPTS {
+        stop.check().ok()?;
+        let c = rng.gen_biguint_range(&BigUint::one(), n);
+        let (a, b) = match (&base, d) {
+            (Some((a0, b0)), _) => {
+                let c2 = &c * &c % n;
+                (a0 * &c2 % n, b0 * (c2 * &c % n) % n)
+            }
+            (None, -4) => (c, BigUint::zero()),
+            (None, _) => (BigUint::zero(), c),
+        };
+        let curve = Curve::new(a, b, n.clone());
+        if !curve.is_nonsingular() {
+            continue;
+        }
+        let point = random_point(&curve, rng)?;
+        let Ok(Some(u)) = curve.mul(&point, &cofactor) else {
+            continue;
+        };
+        if let Ok(None) = curve.mul(&u, q) {
+            return Some(EcppStep {
+                n: n.clone(),
+                d: Some(d),
+                a: curve.a,
+                b: curve.b,
+                m: m.clone(),
+                q: q.clone(),
+                point,
+            });
+        }
+    }
+    None
+}
+
+fn random_point<R: Rng>(curve: &Curve, rng: &mut R) -> Option<Point> {
+    for _ in 0..128 {
+        let x = rng.gen_biguint_below(&curve.n);
+        let rhs = curve.rhs(&x);
+        if let Some(y) = sqrt_mod(&rhs, &curve.n) {
+            let point = Point { x, y };
+            if curve.contains(&point) {
+                return Some(point);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cert::{Certificate, CertificateStep, verify_certificate};
+
+    #[test]
+    fn proves_primes_with_a_valid_chain() {
+        for n in ["618970019642690137449562111", "162259276829213363391578010288127"] {
+            let n: BigUint = n.parse().unwrap();
+            let steps = prove(&n).unwrap();
+            let mut current = n.clone();
+            for step in &steps {
+                assert_eq!(step.n, current);
+                assert!(step.q > q_lower_bound(&step.n) && (&step.m % &step.q).is_zero());
+                current = step.q.clone();
+            }
+            assert!(current.to_u64().is_some_and(is_prime64));
+
+            let steps = steps.into_iter().map(CertificateStep::Ecpp).collect();
+            verify_certificate(&n, &Certificate { n: n.clone(), steps }).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_composites() {
+        let p: BigUint = "618970019642690137449562111".parse().unwrap();
+        for n in [&p * &p, &p * 3u32, &p * 18446744073709551557u64] {
+            assert!(!ecpp_prime_check(&n), "{}", n);
+        }
+        // A strong pseudoprime to the first nine prime bases.
+        assert!(!ecpp_prime_check(&BigUint::from(3825123056546413051u64)));
+        for n in 0..2000u64 {
+            assert_eq!(ecpp_prime_check(&BigUint::from(n)), is_prime64(n), "{}", n);
+        }
+    }
+}
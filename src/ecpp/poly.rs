@@ -0,0 +1,150 @@
+//! Dense polynomials over `Z/nZ`, just enough to find a root of a Hilbert
+//! class polynomial modulo a probable prime.
+
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+
+use crate::arith::{mod_inverse, mod_sub, sqrt_mod, to_residue};
+use crate::cancel::Stop;
+
+/// Coefficients from the constant term up, with no trailing zeros.
+type Poly = Vec<BigUint>;
+
+fn trim(mut p: Poly) -> Poly {
+    while p.last().is_some_and(Zero::is_zero) {
+        p.pop();
+    }
+    p
+}
+
+fn mul_mod(a: &Poly, b: &Poly, modulus: &Poly, n: &BigUint) -> Poly {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![BigUint::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    for c in product.iter_mut() {
+        *c %= n;
+    }
+    rem_monic(product, modulus, n)
+}
+
+/// Remainder modulo a monic polynomial.
+fn rem_monic(mut a: Poly, modulus: &Poly, n: &BigUint) -> Poly {
+    let deg = modulus.len() - 1;
+    while a.len() > deg {
+        let lead = a.pop().unwrap();
+        if lead.is_zero() {
+            continue;
+        }
+        let shift = a.len() - deg;
+        for (i, m) in modulus[..deg].iter().enumerate() {
+            let sub = (&lead * m) % n;
+            a[shift + i] = mod_sub(&a[shift + i], &sub, n);
+        }
+    }
+    trim(a)
+}
+
+/// Makes `p` monic, or returns `None` if its leading coefficient is not invertible.
+fn make_monic(p: Poly, n: &BigUint) -> Option<Poly> {
+    let inv = mod_inverse(p.last()?, n)?;
+    Some(p.into_iter().map(|c| (c * &inv) % n).collect())
+}
+
+/// Monic gcd of `a` and `b`, or `None` if `n` turns out to be composite.
+fn gcd(mut a: Poly, mut b: Poly, n: &BigUint) -> Option<Poly> {
+    a = trim(a);
+    b = trim(b);
+    while !b.is_empty() {
+        let b_monic = make_monic(b, n)?;
+        let r = rem_monic(a, &b_monic, n);
+        a = b_monic;
+        b = r;
+    }
+    make_monic(a, n)
+}
+
+/// `base^exp` modulo `modulus`, or `None` once `stop` says so.
+fn pow_mod(base: &Poly, exp: &BigUint, modulus: &Poly, n: &BigUint, stop: &Stop) -> Option<Poly> {
+    let mut result = vec![BigUint::one()];
+    for i in (0..exp.bits()).rev() {
+        stop.check().ok()?;
+        result = mul_mod(&result, &result, modulus, n);
+        if exp.bit(i) {
+            result = mul_mod(&result, base, modulus, n);
+        }
+    }
+    Some(result)
+}
+
+/// Finds a root modulo `n` of an integer polynomial that splits completely
+/// modulo the (probable) prime `n`. Gives up with `None` once `stop` says so.
+pub fn find_root<R: Rng + ?Sized>(
+    poly: &[BigInt],
+    n: &BigUint,
+    rng: &mut R,
+    stop: &Stop,
+) -> Option<BigUint> {
+    let mut f: Poly = trim(poly.iter().map(|c| to_residue(c, n)).collect());
+    f = make_monic(f, n)?;
+    let half = (n - 1u32) >> 1;
+
+    for _ in 0..256 {
+        match f.len() - 1 {
+            0 => return None,
+            1 => return Some(mod_sub(&BigUint::zero(), &f[0], n)),
+            2 => {
+                // x = (-b + sqrt(b^2 - 4c)) / 2
+                let (c, b) = (&f[0], &f[1]);
+                let disc = mod_sub(&((b * b) % n), &((c * 4u32) % n), n);
+                let root = sqrt_mod(&disc, n)?;
+                let inv2 = mod_inverse(&BigUint::from(2u32), n)?;
+                return Some((mod_sub(&root, b, n) * inv2) % n);
+            }
+            _ => {}
+        }
+
+        // Equal-degree splitting: gcd((x + delta)^((n-1)/2) - 1, f).
+        let delta = rng.gen_biguint_below(n);
+        let power = pow_mod(&vec![delta, BigUint::one()], &half, &f, n, stop)?;
+        let mut shifted = power;
+        if shifted.is_empty() {
+            shifted.push(BigUint::zero());
+        }
+        shifted[0] = mod_sub(&shifted[0], &BigUint::one(), n);
+        let g = gcd(f.clone(), shifted, n)?;
+        let deg = g.len() - 1;
+        if deg == 0 || deg == f.len() - 1 {
+            continue;
+        }
+        // Keep the smaller factor.
+        if 2 * deg < f.len() {
+            f = g;
+        } else {
+            f = quotient_monic(&f, &g, n);
+        }
+    }
+    None
+}
+
+/// Exact quotient `a / b` for monic `b`.
+fn quotient_monic(a: &Poly, b: &Poly, n: &BigUint) -> Poly {
+    let mut rem = a.clone();
+    let deg_b = b.len() - 1;
+    let mut quot = vec![BigUint::zero(); a.len() - deg_b];
+    for k in (0..quot.len()).rev() {
+        let lead = rem[k + deg_b].clone();
+        quot[k] = lead.clone();
+        for (i, c) in b.iter().enumerate() {
+            let sub = (&lead * c) % n;
+            rem[k + i] = mod_sub(&rem[k + i], &sub, n);
+        }
+    }
+    quot
+}
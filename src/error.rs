@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors from prime generation and the proof backends.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PrimeError {
+    /// The proof backend (`gp`) could not be started.
+    BackendMissing,
+    /// The proof backend did not answer within its timeout.
+    BackendTimeout,
+    /// The proof backend exited in the middle of a call, even after a restart.
+    BackendCrashed,
+    /// The proof backend answered with something other than a result.
+    BackendOutputUnparseable(String),
+    /// The requested size has no primes (zero digits, or fewer than two bits).
+    InvalidDigits(usize),
+    /// `top_bits` is zero or exceeds the requested bit length.
+    InvalidTopBits { bits: usize, top_bits: usize },
+    /// The range and residue constraints admit no candidates.
+    Unsatisfiable,
+    /// A parameter is outside the range a standard procedure allows.
+    InvalidParameter(String),
+    /// A procedure with a bounded number of attempts ran out of them.
+    AttemptsExhausted,
+    /// The search was cancelled before a prime was found.
+    Cancelled,
+    /// The search passed its deadline before a prime was found.
+    TimedOut,
+}
+
+impl fmt::Display for PrimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimeError::BackendMissing => write!(f, "PARI/GP (`gp`) could not be started"),
+            PrimeError::BackendTimeout => write!(f, "PARI/GP did not answer in time"),
+            PrimeError::BackendCrashed => write!(f, "PARI/GP exited unexpectedly"),
+            PrimeError::BackendOutputUnparseable(output) => {
+                write!(f, "unexpected PARI/GP output `{}`", output)
+            }
+            PrimeError::InvalidDigits(size) => write!(f, "cannot generate a prime of size {}", size),
+            PrimeError::InvalidTopBits { bits, top_bits } => {
+                write!(f, "cannot set the top {} bits of a {}-bit prime", top_bits, bits)
+            }
+            PrimeError::Unsatisfiable => write!(f, "the constraints admit no primes"),
+            PrimeError::InvalidParameter(reason) => write!(f, "invalid parameter: {}", reason),
+            PrimeError::AttemptsExhausted => write!(f, "no prime found within the allowed attempts"),
+            PrimeError::Cancelled => write!(f, "prime generation was cancelled"),
+            PrimeError::TimedOut => write!(f, "prime generation ran past its deadline"),
+        }
+    }
+}
+
+impl Error for PrimeError {}
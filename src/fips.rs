@@ -0,0 +1,145 @@
+//! RSA prime generation following FIPS 186-5, Appendix A.1.3 (Appendix B.3.3
+//! of FIPS 186-4): random probable primes with Miller–Rabin testing only.
+//!
+//! For a modulus of `nlen` bits and public exponent `e`, each of `p` and `q`
+//! is found as follows, with `k = nlen / 2`:
+//!
+//! 1. Draw a random odd `k`-bit candidate ([`random_candidate`]).
+//! 2. Reject it unless it is at least `sqrt(2) * 2^(k - 1)`, so that `p * q`
+//!    has exactly `nlen` bits.
+//! 3. For `q` only, reject it unless `|p - q| > 2^(k - 100)`.
+//! 4. Reject it unless `gcd(candidate - 1, e) = 1`.
+//! 5. Accept it if it passes [`fips186_5_rounds`] rounds of Miller–Rabin with
+//!    random bases drawn from the same RNG ([`miller_rabin_check_with_rng`]).
+//!
+//! After `5 * k` rejected candidates the procedure fails. Candidates are
+//! trial-divided by small primes ([`sieve_check`]) before step 5; this only
+//! rejects composites early and does not change which numbers are accepted.
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+use crate::candidate::{Size, random_candidate};
+use crate::error::PrimeError;
+use crate::primality::{fips186_5_rounds, miller_rabin_check_with_rng};
+use crate::sieve::sieve_check;
+
+/// Smallest modulus length FIPS 186-5 allows for RSA key generation.
+pub const MIN_MODULUS_BITS: usize = 2048;
+
+/// Generates RSA primes `(p, q)` for an `nlen`-bit modulus and public
+/// exponent `e` with the procedure described in the [module docs](self).
+///
+/// `nlen` must be even and at least [`MIN_MODULUS_BITS`]; `e` must be odd
+/// with `2^16 < e < 2^256`.
+pub fn fips186_5_rsa_primes(nlen: usize, e: &BigUint) -> Result<(BigUint, BigUint), PrimeError> {
+    fips186_5_rsa_primes_with_rng(&mut OsRng, nlen, e)
+}
+
+/// Like [`fips186_5_rsa_primes`], drawing candidates and Miller–Rabin bases
+/// from `rng` instead of [`OsRng`].
+pub fn fips186_5_rsa_primes_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    nlen: usize,
+    e: &BigUint,
+) -> Result<(BigUint, BigUint), PrimeError> {
+    if nlen < MIN_MODULUS_BITS || !nlen.is_multiple_of(2) {
+        return Err(PrimeError::InvalidParameter(format!(
+            "modulus length {} must be even and at least {}",
+            nlen, MIN_MODULUS_BITS
+        )));
+    }
+    if e.is_even() || e.bits() <= 16 || e.bits() > 256 {
+        return Err(PrimeError::InvalidParameter(
+            "public exponent must be odd with 2^16 < e < 2^256".to_string(),
+        ));
+    }
+
+    let k = nlen / 2;
+    // floor(sqrt(2) * 2^(k - 1)) = floor(sqrt(2^(2k - 1))), never exact.
+    let lower = (BigUint::one() << (2 * k - 1)).sqrt() + 1u32;
+    let p = rsa_prime(rng, k, e, &lower, None)?;
+    let q = rsa_prime(rng, k, e, &lower, Some(&p))?;
+    Ok((p, q))
+}
+
+/// Steps 1–5 for one prime; `other` is `p` when generating `q`.
+fn rsa_prime<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    k: usize,
+    e: &BigUint,
+    lower: &BigUint,
+    other: Option<&BigUint>,
+) -> Result<BigUint, PrimeError> {
+    let separation = BigUint::one() << (k - 100);
+    let rounds = fips186_5_rounds(k as u64);
+
+    for _ in 0..5 * k {
+        let candidate = random_candidate(rng, Size::Bits { bits: k, top_bits: 1 })?;
+        if &candidate < lower {
+            continue;
+        }
+        if let Some(p) = other {
+            let distance = if &candidate > p { &candidate - p } else { p - &candidate };
+            if distance <= separation {
+                continue;
+            }
+        }
+        if !(&candidate - 1u32).gcd(e).is_one() {
+            continue;
+        }
+        if sieve_check(&candidate) && miller_rabin_check_with_rng(rng, &candidate, rounds) {
+            return Ok(candidate);
+        }
+    }
+    Err(PrimeError::AttemptsExhausted)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::Num;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    #[test]
+    fn primes_meet_the_appendix_a_1_3_conditions() {
+        let nlen = MIN_MODULUS_BITS;
+        let k = nlen / 2;
+        // sqrt(2) * 2^(k - 1), rounded up in its top 128 bits.
+        let lower = BigUint::from_str_radix("b504f333f9de6484597d89b3754abea0", 16).unwrap();
+        let lower = lower << (k - 128);
+        // 3^11 makes the gcd condition reject every candidate that is 1 mod 3.
+        for e in [65537u32, 177147] {
+            let e = BigUint::from(e);
+            let rng = &mut ChaCha20Rng::seed_from_u64(7);
+            let (p, q) = fips186_5_rsa_primes_with_rng(rng, nlen, &e).unwrap();
+            for prime in [&p, &q] {
+                assert_eq!(prime.bits(), k as u64);
+                assert!(*prime >= lower);
+                assert!((prime - 1u32).gcd(&e).is_one());
+            }
+            let distance = if p > q { &p - &q } else { &q - &p };
+            assert!(distance > BigUint::one() << (k - 100));
+            assert_eq!((&p * &q).bits(), nlen as u64);
+        }
+    }
+
+    #[test]
+    fn unapproved_sizes_and_exponents_are_rejected() {
+        let e = BigUint::from(65537u32);
+        for nlen in [0, 1024, 2047, 2049] {
+            let result = fips186_5_rsa_primes(nlen, &e);
+            assert!(matches!(result, Err(PrimeError::InvalidParameter(_))), "nlen = {}", nlen);
+        }
+        let too_big = (BigUint::one() << 256u32) + 1u32;
+        for e in [3u32.into(), 65535u32.into(), 65538u32.into(), too_big] {
+            let result = fips186_5_rsa_primes(2048, &e);
+            assert!(matches!(result, Err(PrimeError::InvalidParameter(_))), "e = {}", e);
+        }
+    }
+}
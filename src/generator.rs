@@ -0,0 +1,1143 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::cancel::{CancellationToken, Stop};
+use crate::candidate::Size;
+use crate::cert::{Certificate, certify_until};
+use crate::constraint::{Constraints, Space};
+use crate::error::PrimeError;
+use crate::incremental::Incremental;
+use crate::primality::{
+    ProbableTest, ProofBackend, bpsw_check, fips186_5_rounds, is_strong_probable_prime,
+    miller_rabin_check_with_rng,
+};
+use crate::sieve::{Sieve, auto_sieve_bound};
+use crate::stream::{ParPrimeStream, PrimeStream};
+use crate::tune::tune_trial_division;
+use crate::window::Window;
+
+/// Sizes at or above this many digits use the parallel driver under [`Parallelism::Auto`].
+pub const PARALLEL_THRESHOLD_DIGITS: usize = 50;
+
+/// How much evidence a candidate needs before it is returned.
+///
+/// Probable-prime policies are cheap enough for throwaway primes; keys that
+/// must be prime beyond doubt should use [`VerificationPolicy::Proven`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    /// Miller–Rabin with this many random bases
+    /// ([`miller_rabin_check`](crate::miller_rabin_check)). At least one is
+    /// required.
+    ProbableOnly { rounds: usize },
+    /// The Baillie–PSW test ([`bpsw_check`]).
+    Bpsw,
+    /// Miller–Rabin with the round count FIPS 186-5 requires for the
+    /// candidate's size ([`fips186_5_rounds`]).
+    Fips186_5,
+    /// The [`ProbableTest`], then a primality proof from `backend`.
+    Proven { backend: ProofBackend },
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        VerificationPolicy::Proven { backend: ProofBackend::default() }
+    }
+}
+
+impl VerificationPolicy {
+    /// Returns `Ok(true)` if `candidate` satisfies this policy. Random
+    /// Miller–Rabin bases come from [`OsRng`].
+    pub fn verify(&self, candidate: &BigUint) -> Result<bool, PrimeError> {
+        self.verify_with(ProbableTest::default(), candidate)
+    }
+
+    /// Like [`verify`](Self::verify), screening proofs with `test` instead of
+    /// [`probable_prime_check`](crate::primality::probable_prime_check).
+    pub fn verify_with(&self, test: ProbableTest, candidate: &BigUint) -> Result<bool, PrimeError> {
+        self.verify_with_rng(test, candidate, &mut OsRng)
+    }
+
+    /// Like [`verify_with`](Self::verify_with), drawing Miller–Rabin bases
+    /// from `rng`.
+    pub fn verify_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        test: ProbableTest,
+        candidate: &BigUint,
+        rng: &mut R,
+    ) -> Result<bool, PrimeError> {
+        self.validate()?;
+        Ok(self.screen(test, candidate) && self.confirm_until(candidate, rng, &Stop::default())?)
+    }
+
+    /// Fails with [`PrimeError::InvalidParameter`] for Miller–Rabin with no
+    /// rounds, which would accept every odd candidate.
+    pub(crate) fn validate(&self) -> Result<(), PrimeError> {
+        match self {
+            VerificationPolicy::ProbableOnly { rounds: 0 } => Err(PrimeError::InvalidParameter(
+                "Miller–Rabin needs at least one round".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The cheap first stage of the policy: `test` ahead of a proof, nothing
+    /// for the probable-prime policies.
+    pub(crate) fn screen(&self, test: ProbableTest, candidate: &BigUint) -> bool {
+        match self {
+            VerificationPolicy::Proven { .. } => test.check(candidate),
+            _ => true,
+        }
+    }
+
+    /// The rest of the policy, for candidates that passed [`screen`](Self::screen),
+    /// with Miller–Rabin bases from `rng` and proofs abandoned once `stop`
+    /// says so.
+    pub(crate) fn confirm_until<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        candidate: &BigUint,
+        rng: &mut R,
+        stop: &Stop,
+    ) -> Result<bool, PrimeError> {
+        match *self {
+            VerificationPolicy::ProbableOnly { rounds } => {
+                Ok(miller_rabin_check_with_rng(rng, candidate, rounds))
+            }
+            VerificationPolicy::Bpsw => Ok(bpsw_check(candidate)),
+            VerificationPolicy::Fips186_5 => {
+                Ok(miller_rabin_check_with_rng(rng, candidate, fips186_5_rounds(candidate.bits())))
+            }
+            VerificationPolicy::Proven { backend } => backend.prove_until(candidate, stop),
+        }
+    }
+}
+
+/// A strong prime from [`PrimeGenerator::generate_strong`], with the factors
+/// that make it one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StrongPrime {
+    pub p: BigUint,
+    /// A prime factor of `p - 1`.
+    pub r: BigUint,
+    /// A prime factor of `p + 1`.
+    pub s: BigUint,
+    /// A prime factor of `r - 1`.
+    pub t: BigUint,
+}
+
+/// Which search driver to run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Parallelism {
+    /// Sequential below [`PARALLEL_THRESHOLD_DIGITS`], parallel on the global rayon pool above it.
+    #[default]
+    Auto,
+    /// Always search on the calling thread.
+    Sequential,
+    /// Always search in parallel, on a dedicated pool of this many threads (`0` = global pool).
+    /// The pool is built by the first search and reused by later ones.
+    Threads(usize),
+}
+
+/// How candidates are drawn from the search space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// A fresh uniformly random candidate each time.
+    #[default]
+    Random,
+    /// One random start, then consecutive candidates, sieved through a table
+    /// of residues that is updated instead of recomputed. Much cheaper per
+    /// candidate, at the cost of favouring primes that follow long gaps.
+    Incremental,
+    /// Like `Incremental`, but sieving whole windows of consecutive
+    /// candidates as bit arrays against every prime below the sieve bound
+    /// (see [`PrimeGenerator::sieve_bound`]).
+    Windowed,
+}
+
+/// Where a search draws its sieved candidates from.
+pub(crate) enum Candidates {
+    Random(Sieve),
+    Incremental(Incremental),
+    Windowed(Window),
+}
+
+impl Candidates {
+    pub(crate) fn next<R: Rng>(
+        &mut self,
+        space: &Space,
+        rng: &mut R,
+        stop: &Stop,
+    ) -> Result<BigUint, PrimeError> {
+        match self {
+            Candidates::Random(sieve) => sample_sieved(space, sieve, rng, stop),
+            Candidates::Incremental(search) => search.next(space, rng, stop),
+            Candidates::Windowed(window) => window.next(space, rng, stop),
+        }
+    }
+}
+
+/// Draws uniformly random candidates until one passes `sieve`, checking
+/// `stop` before each, and gives up once [`Space::attempts`] have failed.
+fn sample_sieved<R: Rng>(
+    space: &Space,
+    sieve: &Sieve,
+    rng: &mut R,
+    stop: &Stop,
+) -> Result<BigUint, PrimeError> {
+    for _ in 0..space.attempts() {
+        stop.check()?;
+        let candidate = space.sample(rng, stop)?;
+        if sieve.check(&candidate) {
+            return Ok(candidate);
+        }
+    }
+    Err(PrimeError::AttemptsExhausted)
+}
+
+/// Builder for random prime generation.
+///
+/// Randomness comes from any cryptographically secure `R`, by default the
+/// operating system's [`OsRng`]. Each search takes one 32-byte seed from it
+/// and expands that with ChaCha20, so `R` is never shared between threads.
+///
+/// ```no_run
+/// use large_primes::{PrimeGenerator, VerificationPolicy};
+///
+/// let p = PrimeGenerator::new()
+///     .bits(1024)
+///     .verification(VerificationPolicy::Bpsw)
+///     .generate()?;
+/// assert_eq!(p.bits(), 1024);
+/// # Ok::<(), large_primes::PrimeError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrimeGenerator<R = OsRng> {
+    size: Size,
+    constraints: Constraints,
+    rng: R,
+    verification: VerificationPolicy,
+    parallelism: Parallelism,
+    mode: SearchMode,
+    sieve_bound: Option<u32>,
+    trial_limit: Option<u32>,
+    probable_test: ProbableTest,
+    proof_threads: Option<usize>,
+    cancel: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    /// Built on first use, then kept for later searches (and by clones).
+    pool: Option<Arc<rayon::ThreadPool>>,
+    proof_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl PrimeGenerator<OsRng> {
+    /// 100-digit proven primes from [`OsRng`], with automatic parallelism.
+    pub fn new() -> Self {
+        PrimeGenerator {
+            size: Size::Digits(100),
+            constraints: Constraints::new(),
+            rng: OsRng,
+            verification: VerificationPolicy::default(),
+            parallelism: Parallelism::default(),
+            mode: SearchMode::default(),
+            sieve_bound: None,
+            trial_limit: None,
+            probable_test: ProbableTest::default(),
+            proof_threads: None,
+            cancel: None,
+            deadline: None,
+            pool: None,
+            proof_pool: None,
+        }
+    }
+}
+
+impl Default for PrimeGenerator<OsRng> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: RngCore + CryptoRng> PrimeGenerator<R> {
+    /// Generate primes with exactly `digits` decimal digits.
+    pub fn digits(mut self, digits: usize) -> Self {
+        self.size = Size::Digits(digits);
+        self
+    }
+
+    /// Generate primes with exactly `bits` bits.
+    pub fn bits(mut self, bits: usize) -> Self {
+        self.size = Size::Bits { bits, top_bits: 1 };
+        self
+    }
+
+    /// Generate primes of `size`.
+    pub fn size_of(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Also set the top `top_bits` bits (default 1) of each prime.
+    ///
+    /// Only meaningful after [`bits`](Self::bits); digit sizes ignore it.
+    pub fn top_bits(mut self, top_bits: usize) -> Self {
+        if let Size::Bits { top_bits: t, .. } = &mut self.size {
+            *t = top_bits;
+        }
+        self
+    }
+
+    /// Only generate primes satisfying `constraints`.
+    pub fn constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Draw candidates from `rng` instead, e.g. a DRBG or a hardware source.
+    pub fn rng<R2: RngCore + CryptoRng>(self, rng: R2) -> PrimeGenerator<R2> {
+        PrimeGenerator {
+            size: self.size,
+            constraints: self.constraints,
+            rng,
+            verification: self.verification,
+            parallelism: self.parallelism,
+            mode: self.mode,
+            sieve_bound: self.sieve_bound,
+            trial_limit: self.trial_limit,
+            probable_test: self.probable_test,
+            proof_threads: self.proof_threads,
+            cancel: self.cancel,
+            deadline: self.deadline,
+            pool: self.pool,
+            proof_pool: self.proof_pool,
+        }
+    }
+
+    /// Draw candidates from ChaCha20 seeded with `seed`.
+    ///
+    /// The same seed yields the same sequence of primes for any
+    /// [`Parallelism`] and thread count (certificates from
+    /// [`generate_certified`](Self::generate_certified) may still differ).
+    ///
+    /// 64 bits are easily searched, so this is for tests and demos only;
+    /// secrets need [`seed_bytes`](Self::seed_bytes).
+    pub fn seed(self, seed: u64) -> PrimeGenerator<ChaCha20Rng> {
+        self.rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+
+    /// Like [`seed`](Self::seed), with a full 256-bit ChaCha20 key. Kept
+    /// secret and drawn from a good source, it is as strong as [`OsRng`].
+    pub fn seed_bytes(self, seed: [u8; 32]) -> PrimeGenerator<ChaCha20Rng> {
+        self.rng(ChaCha20Rng::from_seed(seed))
+    }
+
+    pub fn verification(mut self, verification: VerificationPolicy) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        self.pool = None;
+        self
+    }
+
+    pub fn search_mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Screen candidates with `test` before the verification policy's proof
+    /// (if any).
+    pub fn probable_test(mut self, test: ProbableTest) -> Self {
+        self.probable_test = test;
+        self
+    }
+
+    /// Split the parallel driver into two stages: the search threads only
+    /// sieve and screen candidates (with the [`ProbableTest`] under
+    /// [`VerificationPolicy::Proven`]), and a dedicated pool of `threads`
+    /// threads proves the survivors, earliest first. Has no effect on the
+    /// sequential driver.
+    pub fn proof_threads(mut self, threads: usize) -> Self {
+        self.proof_threads = Some(threads);
+        self.proof_pool = None;
+        self
+    }
+
+    /// Sieve [`SearchMode::Windowed`] windows with the primes below `bound`
+    /// instead of a bound picked by [`auto_sieve_bound`]. A higher
+    /// [`trial_division_limit`](Self::trial_division_limit) takes precedence.
+    pub fn sieve_bound(mut self, bound: u32) -> Self {
+        self.sieve_bound = Some(bound);
+        self
+    }
+
+    /// Trial-divide candidates by the odd primes below `limit` instead of
+    /// [`SMALL_PRIMES`](crate::sieve::SMALL_PRIMES) (or, for safe primes,
+    /// those below [`SAFE_SIEVE_BOUND`](crate::sieve::SAFE_SIEVE_BOUND)).
+    pub fn trial_division_limit(mut self, limit: u32) -> Self {
+        self.trial_limit = Some(limit);
+        self
+    }
+
+    /// Set the trial-division limit picked by [`tune_trial_division`] for the
+    /// current size and [`probable_test`](Self::probable_test), so set those
+    /// first. Takes a moment of benchmarking. Sizes without primes are left
+    /// for [`generate`](Self::generate) to reject.
+    pub fn tuned_trial_division(self) -> Self {
+        match tune_trial_division(self.size, self.probable_test) {
+            Ok(tuning) => self.trial_division_limit(tuning.limit),
+            Err(_) => self,
+        }
+    }
+
+    /// Stop with [`PrimeError::Cancelled`] once `token` is cancelled.
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Stop with [`PrimeError::TimedOut`] once `deadline` has passed.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Searches until a prime satisfying the verification policy is found.
+    ///
+    /// Fails if the size or constraints admit no candidates, if the policy
+    /// is Miller–Rabin with no rounds, if the proof backend fails, or if the
+    /// search is cancelled or runs past its deadline.
+    pub fn generate(&mut self) -> Result<BigUint, PrimeError> {
+        self.verification.validate()?;
+        let space = self.constraints.resolve(self.size)?;
+        self.generate_in(&space)
+    }
+
+    /// [`generate`](Self::generate) over an already resolved `space`.
+    fn generate_in(&mut self, space: &Space) -> Result<BigUint, PrimeError> {
+        let (verification, test) = (self.verification, self.probable_test);
+        let screen = |c: &BigUint| verification.screen(test, c);
+        let accept = |c: &BigUint, rng: &mut ChaCha20Rng, stop: &Stop| {
+            Ok(verification.confirm_until(c, rng, stop)?.then_some(()))
+        };
+        self.search(space, Sieve::prime(self.trial_limit), screen, accept)
+            .map(|(prime, ())| prime)
+    }
+
+    /// Searches for a safe prime `p = 2q + 1`, with `q` also prime (a Sophie
+    /// Germain prime). Both `p` and `q` must satisfy the verification policy.
+    ///
+    /// Candidates are taken from `p = 11 (mod 12)`, which excludes only the
+    /// safe primes 5 and 7.
+    pub fn generate_safe(&mut self) -> Result<BigUint, PrimeError> {
+        self.verification.validate()?;
+        let space = self
+            .constraints
+            .clone()
+            .residue(11u32, 12u32)
+            .resolve(self.size)?;
+        self.generate_safe_in(&space)
+    }
+
+    /// Searches for a Sophie Germain prime `q`, one with `2q + 1` also prime.
+    ///
+    /// This is [`generate_safe`](Self::generate_safe) with the size applying
+    /// to `q` instead of the safe prime `p = 2q + 1`. The constraints, range
+    /// included, still apply to `p`.
+    pub fn generate_sophie_germain(&mut self) -> Result<BigUint, PrimeError> {
+        self.verification.validate()?;
+        let (lo, hi) = self.size.bounds()?;
+        let (lo, hi) = (lo * 2u32 + 1u32, hi * 2u32 + 1u32);
+        let digits = (hi.bits() as f64 * std::f64::consts::LOG10_2).ceil() as usize;
+        let space = self
+            .constraints
+            .clone()
+            .residue(11u32, 12u32)
+            .resolve_within(lo, hi, digits)?;
+        self.generate_safe_in(&space).map(|p| p >> 1)
+    }
+
+    /// [`generate_safe`](Self::generate_safe) over an already resolved `space`.
+    fn generate_safe_in(&mut self, space: &Space) -> Result<BigUint, PrimeError> {
+        let (verification, test) = (self.verification, self.probable_test);
+        let screen = |p: &BigUint| {
+            let q = p >> 1;
+            // A cheap base-2 test on both before the full policy on either.
+            let two = BigUint::from(2u32);
+            is_strong_probable_prime(&q, &two)
+                && is_strong_probable_prime(p, &two)
+                && verification.screen(test, &q)
+                && verification.screen(test, p)
+        };
+        let accept = |p: &BigUint, rng: &mut ChaCha20Rng, stop: &Stop| {
+            let q = p >> 1;
+            let prime = verification.confirm_until(&q, rng, stop)?
+                && verification.confirm_until(p, rng, stop)?;
+            Ok(prime.then_some(()))
+        };
+        self.search(space, Sieve::safe(self.trial_limit), screen, accept)
+            .map(|(prime, ())| prime)
+    }
+
+    /// Searches for a strong prime `p` in Gordon's sense: `p - 1` has a large
+    /// prime factor `r`, `p + 1` a large prime factor `s`, and `r - 1` a
+    /// large prime factor `t`. All four satisfy the verification policy.
+    ///
+    /// `r` and `s` have about half the bits of `p`, so sizes below 128 bits
+    /// are rejected. The constraints apply to `p` only; the conditions on it
+    /// are added as residue classes modulo `2r` and `s`.
+    pub fn generate_strong(&mut self) -> Result<StrongPrime, PrimeError> {
+        self.verification.validate()?;
+        let bits = self.size.bounds()?.0.bits() as usize;
+        if bits < 128 {
+            return Err(PrimeError::InvalidParameter(format!(
+                "strong primes need at least 128 bits, not {}",
+                bits
+            )));
+        }
+        let half = |bits| Size::Bits { bits, top_bits: 1 };
+        let space = Constraints::new().resolve(half(bits / 2 - 28))?;
+        let t = self.generate_in(&space)?;
+        let space = Constraints::new().residue(1u32, &t << 1).resolve(half(bits / 2 - 12))?;
+        let r = self.generate_in(&space)?;
+        let space = Constraints::new().resolve(half(bits / 2 - 12))?;
+        let s = self.generate_in(&space)?;
+        // 2rs has about 23 bits fewer than p, leaving room for ~2^22 candidates.
+        let constraints = self.constraints.clone().residue(1u32, &r << 1);
+        let space = constraints.residue(&s - 1u32, s.clone()).resolve(self.size)?;
+        let p = self.generate_in(&space)?;
+        Ok(StrongPrime { p, r, s, t })
+    }
+
+    /// Like [`generate`](Self::generate), but also returns a primality
+    /// certificate from [`certify`](crate::cert::certify).
+    ///
+    /// The certificate is the proof, so the configured verification policy
+    /// is not consulted.
+    pub fn generate_certified(&mut self) -> Result<(BigUint, Certificate), PrimeError> {
+        let test = self.probable_test;
+        let space = self.constraints.resolve(self.size)?;
+        self.search(&space, Sieve::prime(self.trial_limit), |c| test.check(c), |c, _, stop| {
+            certify_until(c, stop)
+        })
+    }
+
+    /// An endless iterator over distinct primes of the configured size and
+    /// constraints, each satisfying the verification policy.
+    ///
+    /// The search state (sieve tables, walk position) carries over from one
+    /// prime to the next. The stream runs on the calling thread; see
+    /// [`par_stream`](Self::par_stream) for a parallel one.
+    pub fn stream(mut self) -> Result<PrimeStream, PrimeError> {
+        self.verification.validate()?;
+        let space = self.constraints.resolve(self.size)?;
+        let (seed, candidates) = self.start(&space, Sieve::prime(self.trial_limit));
+        Ok(PrimeStream::new(
+            space,
+            candidates,
+            seed,
+            self.verification,
+            self.probable_test,
+            self.stop(),
+        ))
+    }
+
+    /// Like [`stream`](Self::stream), with workers searching ahead in the
+    /// background and yielding the same primes in the same order.
+    ///
+    /// The workers park while enough primes are buffered, so they stay off
+    /// the global pool and run on the generator's own: one thread for
+    /// [`Parallelism::Sequential`], `n` for `Threads(n)`, and as many as the
+    /// global pool otherwise. A `Threads(n)` pool already built by earlier
+    /// searches is reused. Each worker proves its own candidates;
+    /// [`proof_threads`](Self::proof_threads) does not apply.
+    pub fn par_stream(mut self) -> Result<ParPrimeStream, PrimeError> {
+        self.verification.validate()?;
+        let space = self.constraints.resolve(self.size)?;
+        let (seed, candidates) = self.start(&space, Sieve::prime(self.trial_limit));
+        Ok(ParPrimeStream::new(
+            Slots::new(space, candidates, seed),
+            self.pool(),
+            self.verification,
+            self.probable_test,
+            self.stop(),
+        ))
+    }
+
+    /// Takes a search seed from the RNG and sets up the configured candidate
+    /// source on `space`.
+    fn start(&mut self, space: &Space, sieve: Sieve) -> ([u8; 32], Candidates) {
+        let mut seed = [0u8; 32];
+        self.rng.fill_bytes(&mut seed);
+        let setup = &mut slot_rng(seed, 0);
+        let candidates = match self.mode {
+            SearchMode::Random => Candidates::Random(sieve),
+            SearchMode::Incremental => Candidates::Incremental(Incremental::new(space, sieve, setup)),
+            SearchMode::Windowed => {
+                let bound = self.sieve_bound.unwrap_or_else(|| auto_sieve_bound(space.digits()));
+                Candidates::Windowed(Window::new(space, sieve, bound, setup))
+            }
+        };
+        (seed, candidates)
+    }
+
+    fn stop(&self) -> Stop {
+        Stop::new(self.cancel.clone(), self.deadline)
+    }
+
+    /// Runs the configured driver over `space` until `accept` returns
+    /// `Ok(Some(_))` for a candidate passing `sieve` and the cheap `screen`,
+    /// or stops at its first error or when cancelled or out of time.
+    /// `accept` is handed the [`Stop`] its long-running checks should honour.
+    ///
+    /// Each search takes one seed from the generator's RNG. Candidate `i`
+    /// (from 1) is drawn from ChaCha20 stream `i` of that seed, and stream 0
+    /// picks the starting point of incremental and windowed walks, so every
+    /// driver and thread count sees the same candidates in the same order.
+    fn search<T, S, F>(
+        &mut self,
+        space: &Space,
+        sieve: Sieve,
+        screen: S,
+        accept: F,
+    ) -> Result<(BigUint, T), PrimeError>
+    where
+        T: Send,
+        S: Fn(&BigUint) -> bool + Sync,
+        F: Fn(&BigUint, &mut ChaCha20Rng, &Stop) -> Result<Option<T>, PrimeError> + Sync,
+    {
+        let stop = &self.stop();
+        let (seed, candidates) = self.start(space, sieve);
+        let pool = match self.parallelism {
+            Parallelism::Sequential => {
+                return search_sequential(space, candidates, seed, stop, screen, accept);
+            }
+            Parallelism::Auto if space.digits() < PARALLEL_THRESHOLD_DIGITS => {
+                return search_sequential(space, candidates, seed, stop, screen, accept);
+            }
+            Parallelism::Auto | Parallelism::Threads(0) => None,
+            Parallelism::Threads(_) => Some(self.pool()),
+        };
+        let slots = Slots::new(space.clone(), candidates, seed);
+        match self.proof_threads {
+            Some(threads) => {
+                let proof_pool =
+                    self.proof_pool.get_or_insert_with(|| Arc::new(build_pool(threads)));
+                search_pipelined(&slots, stop, pool.as_deref(), proof_pool, screen, accept)
+            }
+            None => search_parallel(&slots, stop, pool.as_deref(), screen, accept),
+        }
+    }
+
+    /// The generator's own pool: `n` threads for `Threads(n)`, one for
+    /// `Sequential`, and as many as the global pool otherwise.
+    fn pool(&mut self) -> Arc<rayon::ThreadPool> {
+        let threads = match self.parallelism {
+            Parallelism::Sequential => 1,
+            Parallelism::Auto | Parallelism::Threads(0) => rayon::current_num_threads(),
+            Parallelism::Threads(threads) => threads,
+        };
+        self.pool.get_or_insert_with(|| Arc::new(build_pool(threads))).clone()
+    }
+}
+
+pub(crate) fn build_pool(threads: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build rayon thread pool")
+}
+
+/// Runs `worker` on every thread of `pool`, or of the global pool.
+fn broadcast<W: Fn(rayon::BroadcastContext<'_>) + Sync>(pool: Option<&rayon::ThreadPool>, worker: W) {
+    match pool {
+        Some(pool) => pool.broadcast(worker),
+        None => rayon::broadcast(worker),
+    };
+}
+
+/// ChaCha20 stream `slot` of a search seeded with `seed`.
+pub(crate) fn slot_rng(seed: [u8; 32], slot: u64) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    rng.set_stream(slot);
+    rng
+}
+
+/// The second half of [`slot_rng`]`(seed, slot)`, for the random bases of
+/// the slot's probable-prime tests: far past anything drawing the candidate
+/// could consume.
+pub(crate) fn test_rng(seed: [u8; 32], slot: u64) -> ChaCha20Rng {
+    let mut rng = slot_rng(seed, slot);
+    rng.set_word_pos(1 << 67);
+    rng
+}
+
+fn search_sequential<T, S, F>(
+    space: &Space,
+    mut candidates: Candidates,
+    seed: [u8; 32],
+    stop: &Stop,
+    screen: S,
+    accept: F,
+) -> Result<(BigUint, T), PrimeError>
+where
+    S: Fn(&BigUint) -> bool,
+    F: Fn(&BigUint, &mut ChaCha20Rng, &Stop) -> Result<Option<T>, PrimeError>,
+{
+    let mut slot = 0;
+    loop {
+        slot += 1;
+        let candidate = candidates.next(space, &mut slot_rng(seed, slot), stop)?;
+
+        if screen(&candidate)
+            && let Some(evidence) = accept(&candidate, &mut test_rng(seed, slot), stop)?
+        {
+            return Ok((candidate, evidence));
+        }
+    }
+}
+
+/// Hands out candidate slots, in order, to the workers of a parallel search:
+/// random candidates are drawn concurrently, walk steps one at a time under
+/// a lock.
+pub(crate) struct Slots {
+    space: Space,
+    seed: [u8; 32],
+    random: Option<Sieve>,
+    walk: Mutex<Candidates>,
+    next: AtomicU64,
+}
+
+impl Slots {
+    pub(crate) fn new(space: Space, candidates: Candidates, seed: [u8; 32]) -> Self {
+        let random = match &candidates {
+            Candidates::Random(sieve) => Some(sieve.clone()),
+            _ => None,
+        };
+        Slots { space, seed, random, walk: Mutex::new(candidates), next: AtomicU64::new(1) }
+    }
+
+    pub(crate) fn space(&self) -> &Space {
+        &self.space
+    }
+
+    pub(crate) fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Claims the next slot and draws its candidate, or returns `None` if
+    /// that slot comes after the `settled` one.
+    pub(crate) fn claim(
+        &self,
+        settled: &AtomicU64,
+        stop: &Stop,
+    ) -> Option<(u64, Result<BigUint, PrimeError>)> {
+        let rng = |slot| slot_rng(self.seed, slot);
+        match &self.random {
+            Some(sieve) => {
+                let slot = self.next.fetch_add(1, Ordering::Relaxed);
+                (slot < settled.load(Ordering::Relaxed))
+                    .then(|| (slot, sample_sieved(&self.space, sieve, &mut rng(slot), stop)))
+            }
+            None => {
+                let mut walk = self.walk.lock().unwrap();
+                let slot = self.next.fetch_add(1, Ordering::Relaxed);
+                (slot < settled.load(Ordering::Relaxed))
+                    .then(|| (slot, walk.next(&self.space, &mut rng(slot), stop)))
+            }
+        }
+    }
+}
+
+/// Runs `accept` for `slot` under a [`Stop`] that fires once an earlier slot
+/// settles, returning `None` if the candidate was rejected or abandoned.
+fn settle_slot<T, F>(
+    slots: &Slots,
+    slot: u64,
+    candidate: BigUint,
+    settled: &Arc<AtomicU64>,
+    stop: &Stop,
+    accept: &F,
+) -> Option<Result<(BigUint, T), PrimeError>>
+where
+    F: Fn(&BigUint, &mut ChaCha20Rng, &Stop) -> Result<Option<T>, PrimeError>,
+{
+    match accept(&candidate, &mut test_rng(slots.seed, slot), &stop.for_slot(settled, slot)) {
+        Ok(evidence) => evidence.map(|e| Ok((candidate, e))),
+        // Abandoned because an earlier slot settled first.
+        Err(PrimeError::Cancelled) if stop.check().is_ok() => None,
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Runs one worker per thread of `pool` (or the global pool). Each worker
+/// claims the next slot, draws and sieves its candidate and tests it, until
+/// a slot settles the search with a prime or an error.
+///
+/// Once slot `k` has settled, workers stop claiming slots and checks still
+/// running on slots after `k` are abandoned through their [`Stop`]. Slots
+/// before `k` run to completion, so the earliest settling slot wins and the
+/// result matches [`search_sequential`] for any number of threads.
+fn search_parallel<T, S, F>(
+    slots: &Slots,
+    stop: &Stop,
+    pool: Option<&rayon::ThreadPool>,
+    screen: S,
+    accept: F,
+) -> Result<(BigUint, T), PrimeError>
+where
+    T: Send,
+    S: Fn(&BigUint) -> bool + Sync,
+    F: Fn(&BigUint, &mut ChaCha20Rng, &Stop) -> Result<Option<T>, PrimeError> + Sync,
+{
+    let settled = Arc::new(AtomicU64::new(u64::MAX));
+    let outcome = Mutex::new(None);
+
+    broadcast(pool, |_| {
+        while let Some((slot, candidate)) = slots.claim(&settled, stop) {
+            let result = match candidate {
+                Ok(c) if !screen(&c) => continue,
+                Ok(c) => match settle_slot(slots, slot, c, &settled, stop, &accept) {
+                    Some(result) => result,
+                    None => continue,
+                },
+                Err(e) => Err(e),
+            };
+            let mut outcome = outcome.lock().unwrap();
+            if slot < settled.load(Ordering::Relaxed) {
+                settled.store(slot, Ordering::Relaxed);
+                *outcome = Some(result);
+            }
+        }
+    });
+
+    outcome
+        .into_inner()
+        .unwrap()
+        .expect("workers only stop once a slot has settled")
+}
+
+/// State shared by the two stages of [`search_pipelined`].
+struct Pipeline<T> {
+    /// Screened candidates waiting for the proof pool, by slot.
+    queue: BTreeMap<u64, BigUint>,
+    /// Search workers still running.
+    producers: usize,
+    outcome: Option<Result<(BigUint, T), PrimeError>>,
+}
+
+/// Like [`search_parallel`], but the search workers only sieve and `screen`
+/// candidates and queue the survivors; one worker per thread of
+/// `proof_pool` takes the earliest queued slot and runs `accept` on it.
+/// Proof workers drain the queue until every search worker has stopped, so
+/// no screened slot before the settled one is skipped.
+///
+/// The queue holds at most two candidates per proof thread, so the search
+/// workers keep a little ahead of the proofs without drawing candidates that
+/// are bound to be superseded. Slots still settle earliest-first, so the
+/// result is the same as with either other driver.
+fn search_pipelined<T, S, F>(
+    slots: &Slots,
+    stop: &Stop,
+    pool: Option<&rayon::ThreadPool>,
+    proof_pool: &rayon::ThreadPool,
+    screen: S,
+    accept: F,
+) -> Result<(BigUint, T), PrimeError>
+where
+    T: Send,
+    S: Fn(&BigUint) -> bool + Sync,
+    F: Fn(&BigUint, &mut ChaCha20Rng, &Stop) -> Result<Option<T>, PrimeError> + Sync,
+{
+    let settled = Arc::new(AtomicU64::new(u64::MAX));
+    let capacity = 2 * proof_pool.current_num_threads();
+    let producers = pool.map_or_else(rayon::current_num_threads, |p| p.current_num_threads());
+    let state = Mutex::new(Pipeline { queue: BTreeMap::new(), producers, outcome: None });
+    let changed = Condvar::new();
+
+    let settle = |slot: u64, result| {
+        let mut state = state.lock().unwrap();
+        if slot < settled.load(Ordering::Relaxed) {
+            settled.store(slot, Ordering::Relaxed);
+            state.outcome = Some(result);
+        }
+        changed.notify_all();
+    };
+
+    let search = |_: rayon::BroadcastContext<'_>| {
+        while let Some((slot, candidate)) = slots.claim(&settled, stop) {
+            match candidate {
+                Ok(c) if !screen(&c) => {}
+                Ok(c) => {
+                    let mut state = state.lock().unwrap();
+                    while state.queue.len() >= capacity && slot < settled.load(Ordering::Relaxed) {
+                        state = changed.wait(state).unwrap();
+                    }
+                    if slot < settled.load(Ordering::Relaxed) {
+                        state.queue.insert(slot, c);
+                        changed.notify_all();
+                    }
+                }
+                Err(e) => settle(slot, Err(e)),
+            }
+        }
+        state.lock().unwrap().producers -= 1;
+        changed.notify_all();
+    };
+
+    let prove = |_: rayon::BroadcastContext<'_>| loop {
+        let (slot, candidate) = {
+            let mut state = state.lock().unwrap();
+            loop {
+                if let Some(entry) = state.queue.pop_first() {
+                    changed.notify_all();
+                    break entry;
+                }
+                if state.producers == 0 {
+                    return;
+                }
+                state = changed.wait(state).unwrap();
+            }
+        };
+        if slot >= settled.load(Ordering::Relaxed) {
+            continue;
+        }
+        if let Some(result) = settle_slot(slots, slot, candidate, &settled, stop, &accept) {
+            settle(slot, result);
+        }
+    };
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| proof_pool.broadcast(prove));
+        broadcast(pool, search);
+    });
+
+    state
+        .into_inner()
+        .unwrap()
+        .outcome
+        .expect("workers only stop once a slot has settled")
+}
+
+/// Generates a proven prime with exactly `digits` decimal digits.
+///
+/// Runs in parallel from [`PARALLEL_THRESHOLD_DIGITS`] digits up.
+pub fn gen_rand_large_prime(digits: usize) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new().digits(digits).generate()
+}
+
+/// Like [`gen_rand_large_prime`], with the given verification policy.
+pub fn gen_rand_large_prime_with(
+    digits: usize,
+    verification: VerificationPolicy,
+) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new()
+        .digits(digits)
+        .verification(verification)
+        .generate()
+}
+
+/// Like [`gen_rand_large_prime`], always on the calling thread.
+pub fn gen_rand_large_prime_sequential(digits: usize) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new()
+        .digits(digits)
+        .parallelism(Parallelism::Sequential)
+        .generate()
+}
+
+/// Like [`gen_rand_large_prime`], always on the global rayon pool.
+pub fn gen_rand_large_prime_parallel(digits: usize) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new()
+        .digits(digits)
+        .parallelism(Parallelism::Threads(0))
+        .generate()
+}
+
+/// Like [`gen_rand_large_prime`], drawing candidates from ChaCha20 seeded
+/// with `seed`: the same seed always gives the same prime. For tests and
+/// demos only, see [`PrimeGenerator::seed`].
+pub fn gen_rand_large_prime_seeded(digits: usize, seed: u64) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new().digits(digits).seed(seed).generate()
+}
+
+/// Like [`gen_rand_large_prime`], restricted to primes satisfying
+/// `constraints`.
+pub fn gen_rand_large_prime_constrained(
+    digits: usize,
+    constraints: &Constraints,
+) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new()
+        .digits(digits)
+        .constraints(constraints.clone())
+        .generate()
+}
+
+/// Generates a proven prime with exactly `bits` bits, the top `top_bits` of
+/// which are set.
+pub fn gen_prime_bits(bits: usize, top_bits: usize) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new().bits(bits).top_bits(top_bits).generate()
+}
+
+/// Generates a proven safe prime `p = 2q + 1` of the given size.
+///
+/// `(p - 1) / 2` is then a Sophie Germain prime.
+pub fn gen_safe_prime(size: Size) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new().size_of(size).generate_safe()
+}
+
+/// Generates a proven Sophie Germain prime `q` of the given size, i.e. one for
+/// which `2q + 1` is also prime.
+pub fn gen_sophie_germain_prime(size: Size) -> Result<BigUint, PrimeError> {
+    PrimeGenerator::new().size_of(size).generate_sophie_germain()
+}
+
+/// Like [`gen_rand_large_prime`], also returning a primality certificate.
+pub fn gen_rand_large_prime_certified(digits: usize) -> Result<(BigUint, Certificate), PrimeError> {
+    PrimeGenerator::new().digits(digits).generate_certified()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_searches_agree_across_drivers() {
+        let parallelism = [
+            Parallelism::Sequential,
+            Parallelism::Threads(1),
+            Parallelism::Threads(2),
+            Parallelism::Threads(4),
+            Parallelism::Threads(0),
+        ];
+        for mode in [SearchMode::Random, SearchMode::Incremental, SearchMode::Windowed] {
+            let generator = |parallelism| {
+                PrimeGenerator::new()
+                    .bits(160)
+                    .verification(VerificationPolicy::Bpsw)
+                    .search_mode(mode)
+                    .parallelism(parallelism)
+                    .seed_bytes([7; 32])
+            };
+            let prime = generator(Parallelism::Sequential).generate().unwrap();
+            let safe = generator(Parallelism::Sequential).generate_safe().unwrap();
+            for parallelism in parallelism {
+                assert_eq!(generator(parallelism).generate().unwrap(), prime, "{:?}", mode);
+                assert_eq!(generator(parallelism).generate_safe().unwrap(), safe, "{:?}", mode);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_and_pipelined_drivers_return_the_sequential_prime() {
+        let generator = |parallelism| {
+            PrimeGenerator::new()
+                .bits(96)
+                .verification(VerificationPolicy::Proven { backend: ProofBackend::Ecpp })
+                .parallelism(parallelism)
+                .seed(9)
+        };
+        let prime = generator(Parallelism::Sequential).generate().unwrap();
+        let (certified, _) = generator(Parallelism::Sequential).generate_certified().unwrap();
+        for threads in [0, 1, 3] {
+            for proof_threads in [None, Some(2)] {
+                let generator = || {
+                    let generator = generator(Parallelism::Threads(threads));
+                    match proof_threads {
+                        Some(proof_threads) => generator.proof_threads(proof_threads),
+                        None => generator,
+                    }
+                };
+                let context = format!("{} threads, {:?} proof threads", threads, proof_threads);
+                assert_eq!(generator().generate().unwrap(), prime, "{}", context);
+                let (p, cert) = generator().generate_certified().unwrap();
+                assert_eq!(p, certified, "{}", context);
+                crate::cert::verify_certificate(&p, &cert).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn zero_round_miller_rabin_is_rejected() {
+        let policy = VerificationPolicy::ProbableOnly { rounds: 0 };
+        let nine = BigUint::from(9u32);
+        assert!(matches!(policy.verify(&nine), Err(PrimeError::InvalidParameter(_))));
+        let generator = || PrimeGenerator::new().bits(64).verification(policy);
+        assert!(matches!(generator().generate(), Err(PrimeError::InvalidParameter(_))));
+        assert!(matches!(generator().generate_safe(), Err(PrimeError::InvalidParameter(_))));
+        assert!(matches!(generator().stream(), Err(PrimeError::InvalidParameter(_))));
+        assert!(matches!(generator().par_stream(), Err(PrimeError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn thread_pools_are_built_once() {
+        let mut generator = PrimeGenerator::new()
+            .bits(64)
+            .verification(VerificationPolicy::Bpsw)
+            .parallelism(Parallelism::Threads(2))
+            .proof_threads(1)
+            .seed(1);
+        generator.generate().unwrap();
+        let pool = generator.pool.clone().unwrap();
+        let proof_pool = generator.proof_pool.clone().unwrap();
+        generator.generate_safe().unwrap();
+        assert!(Arc::ptr_eq(generator.pool.as_ref().unwrap(), &pool));
+        assert!(Arc::ptr_eq(generator.proof_pool.as_ref().unwrap(), &proof_pool));
+        assert_eq!(pool.current_num_threads(), 2);
+
+        let generator = generator.parallelism(Parallelism::Threads(3)).proof_threads(2);
+        assert!(generator.pool.is_none() && generator.proof_pool.is_none());
+    }
+
+    #[test]
+    fn sophie_germain_primes_take_the_size_of_q() {
+        for size in [Size::Bits { bits: 64, top_bits: 2 }, Size::Digits(20)] {
+            let (lo, hi) = size.bounds().unwrap();
+            let q = PrimeGenerator::new()
+                .size_of(size)
+                .verification(VerificationPolicy::Bpsw)
+                .seed(5)
+                .generate_sophie_germain()
+                .unwrap();
+            assert!(lo <= q && q < hi, "{} is not {:?}", q, size);
+            assert!(bpsw_check(&q) && bpsw_check(&(&q * 2u32 + 1u32)), "{}", q);
+        }
+
+        let below = Constraints::new().range(0u32, 1u64 << 62);
+        let safe = PrimeGenerator::new().bits(64).constraints(below).generate_safe();
+        assert_eq!(safe, Err(PrimeError::Unsatisfiable));
+    }
+
+    #[test]
+    fn strong_primes_have_the_gordon_structure() {
+        use num_integer::Integer;
+
+        let generator = || {
+            PrimeGenerator::new()
+                .bits(160)
+                .verification(VerificationPolicy::Bpsw)
+                .constraints(Constraints::new().residue(2u32, 5u32))
+                .seed(8)
+        };
+        let StrongPrime { p, r, s, t } = generator().generate_strong().unwrap();
+        assert!([&p, &r, &s, &t].into_iter().all(bpsw_check));
+        assert_eq!([p.bits(), r.bits(), s.bits(), t.bits()], [160, 68, 68, 52]);
+        assert!((&p - 1u32).is_multiple_of(&r));
+        assert!((&p + 1u32).is_multiple_of(&s));
+        assert!((&r - 1u32).is_multiple_of(&t));
+        assert_eq!(&p % 5u32, BigUint::from(2u32));
+
+        let small = generator().bits(127).generate_strong();
+        assert!(matches!(small, Err(PrimeError::InvalidParameter(_))));
+    }
+}
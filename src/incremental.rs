@@ -0,0 +1,150 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::ToPrimitive;
+use rand::Rng;
+
+use crate::cancel::Stop;
+use crate::constraint::Space;
+use crate::error::PrimeError;
+use crate::sieve::Sieve;
+
+/// Candidates below this many bits may equal a sieving prime, so the residue
+/// table is bypassed for them.
+const SMALL_CANDIDATE_BITS: u64 = 32;
+
+/// OpenSSL-style incremental search: one random start, then consecutive
+/// points of the progression, with a table of residues modulo every sieving
+/// prime updated by word additions instead of recomputed per candidate.
+///
+/// When the end of the progression is reached, a fresh random start is drawn.
+#[derive(Clone, Debug)]
+pub(crate) struct Incremental {
+    sieve: Sieve,
+    base: BigUint,
+    offset: u64,
+    remaining: u64,
+    residues: Vec<u32>,
+    deltas: Vec<u32>,
+}
+
+impl Incremental {
+    pub(crate) fn new<R: Rng + ?Sized>(space: &Space, sieve: Sieve, rng: &mut R) -> Self {
+        let deltas = sieve
+            .primes()
+            .iter()
+            .map(|&r| (space.step() % r).to_u32().unwrap())
+            .collect();
+        let mut search = Incremental {
+            sieve,
+            base: BigUint::default(),
+            offset: 0,
+            remaining: 0,
+            residues: Vec::new(),
+            deltas,
+        };
+        search.restart(space, rng);
+        search
+    }
+
+    fn restart<R: Rng + ?Sized>(&mut self, space: &Space, rng: &mut R) {
+        let k = rng.gen_biguint_below(space.count());
+        self.base = space.nth(&k);
+        self.offset = 0;
+        self.remaining = (space.count() - k).to_u64().unwrap_or(u64::MAX);
+        self.residues = self
+            .sieve
+            .primes()
+            .iter()
+            .map(|&r| (&self.base % r).to_u32().unwrap())
+            .collect();
+    }
+
+    fn advance(&mut self) {
+        let primes = self.sieve.primes();
+        for ((rem, &delta), &r) in self.residues.iter_mut().zip(&self.deltas).zip(primes) {
+            *rem += delta;
+            if *rem >= r {
+                *rem -= r;
+            }
+        }
+        self.offset += 1;
+        self.remaining -= 1;
+    }
+
+    /// Returns the next candidate that survives the sieve and the excluded
+    /// residues of `space`, checking `stop` before each step and giving up
+    /// after [`Space::attempts`] steps.
+    pub(crate) fn next<R: Rng + ?Sized>(
+        &mut self,
+        space: &Space,
+        rng: &mut R,
+        stop: &Stop,
+    ) -> Result<BigUint, PrimeError> {
+        for _ in 0..space.attempts() {
+            stop.check()?;
+            if self.remaining == 0 {
+                self.restart(space, rng);
+            }
+            let sieve = &self.sieve;
+            let passes = self.residues.iter().all(|&rem| sieve.admits_residue(rem));
+            let candidate = (passes || self.base.bits() <= SMALL_CANDIDATE_BITS)
+                .then(|| &self.base + space.step() * self.offset);
+            self.advance();
+
+            let Some(candidate) = candidate else {
+                continue;
+            };
+            if candidate.bits() <= SMALL_CANDIDATE_BITS && !self.sieve.check(&candidate) {
+                continue;
+            }
+            if space.admits(&candidate) {
+                return Ok(candidate);
+            }
+        }
+        Err(PrimeError::AttemptsExhausted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::candidate::Size;
+    use crate::constraint::Constraints;
+
+    #[test]
+    fn candidates_satisfy_the_constraints() {
+        let lo = BigUint::from(10u32).pow(30);
+        // 2000 points of the first progression, so walks restart.
+        let hi = &lo + 33792u32 * 2000u32;
+        let excluded = [(1, 5), (3, 7)];
+        let (prime, safe) = ([(5u32, 12u32), (1, 1024), (7, 11)], [(11u32, 12u32), (7, 11)]);
+        for (residues, narrow, sieve) in [
+            (&prime[..], false, Sieve::prime(None)),
+            (&prime[..], true, Sieve::prime(None)),
+            (&safe[..], false, Sieve::safe(None)),
+        ] {
+            let mut constraints = Constraints::new();
+            for &(r, m) in residues {
+                constraints = constraints.residue(r, m);
+            }
+            for &(r, m) in &excluded {
+                constraints = constraints.exclude(r, m);
+            }
+            if narrow {
+                constraints = constraints.range(lo.clone(), hi.clone());
+            }
+            let space = constraints.resolve(Size::Digits(31)).unwrap();
+            let rng = &mut ChaCha20Rng::seed_from_u64(4);
+            let mut search = Incremental::new(&space, sieve.clone(), rng);
+            for _ in 0..300 {
+                let p = search.next(&space, rng, &Stop::default()).unwrap();
+                assert!(p.bit(0) && sieve.check(&p), "{}", p);
+                assert!(residues.iter().all(|&(r, m)| &p % m == BigUint::from(r)), "{}", p);
+                assert!(excluded.iter().all(|&(r, m)| &p % m != BigUint::from(r)), "{}", p);
+                assert!(!narrow || (lo <= p && p < hi), "{}", p);
+            }
+        }
+    }
+}
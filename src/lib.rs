@@ -0,0 +1,58 @@
+//! Random large prime generation.
+//!
+//! Candidates are drawn uniformly at random, trial-divided against a table of
+//! small primes, screened with a probable-prime test and finally proven either
+//! natively with ECPP or with PARI/GP. [`PrimeGenerator`] exposes each of these
+//! knobs; the `gen_rand_large_prime*` functions are shortcuts for the common
+//! cases.
+
+mod arith;
+pub mod cancel;
+pub mod candidate;
+pub mod cert;
+pub mod constraint;
+mod der;
+pub mod dh;
+pub mod ecpp;
+mod error;
+pub mod fips;
+pub mod generator;
+mod incremental;
+pub mod pari;
+pub mod primality;
+pub mod primorial;
+pub mod rsa;
+mod sha256;
+pub mod sieve;
+pub mod stream;
+pub mod tune;
+mod window;
+
+pub use cancel::CancellationToken;
+pub use candidate::{
+    Size, generate_prime_candidate, generate_prime_candidate_bits,
+    generate_prime_candidate_constrained,
+};
+pub use cert::{Certificate, certify};
+pub use constraint::Constraints;
+pub use generator::{
+    Parallelism, PrimeGenerator, SearchMode, StrongPrime, VerificationPolicy, gen_prime_bits,
+    gen_rand_large_prime, gen_rand_large_prime_certified, gen_rand_large_prime_constrained,
+    gen_rand_large_prime_parallel, gen_rand_large_prime_seeded, gen_rand_large_prime_sequential,
+    gen_rand_large_prime_with, gen_safe_prime, gen_sophie_germain_prime,
+};
+pub use ecpp::ecpp_prime_check;
+pub use error::PrimeError;
+pub use fips::fips186_5_rsa_primes;
+pub use primorial::primorial_check;
+pub use primality::{
+    ProbableTest, ProofBackend, bpsw_check, deterministic_prime_check, fips186_5_rounds,
+    is_strong_lucas_probable_prime, miller_rabin_check, miller_rabin_check_with_rng,
+    probable_prime_check, probable_prime_status,
+};
+pub use sieve::{
+    SMALL_PRIMES, auto_sieve_bound, odd_primes_below, safe_sieve_check, sieve_check,
+    trial_division,
+};
+pub use stream::{ParPrimeStream, PrimeStream};
+pub use tune::{TrialDivisionTuning, tune_trial_division};
@@ -1,176 +1,553 @@
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Instant;
-use std::process::Command;
-use std::path::Path;
+use std::env;
 use std::fs;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-
-use num_bigint::{BigUint, RandBigInt};
-use num_prime::{nt_funcs::is_prime, Primality, PrimalityTestConfig};
-use num_traits::{FromPrimitive, One, Pow, Zero};
-use rand::thread_rng;
+use large_primes::cert::{Certificate, certify, parse_certificate, verify_certificate};
+use large_primes::pari::GpSession;
+use large_primes::sieve::sieve_check;
+use large_primes::{Parallelism, PrimeError, PrimeGenerator, ProofBackend, Size, VerificationPolicy};
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use rayon::prelude::*;
 
-const SMALL_PRIMES: [u32; 201] = [
-    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
-    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
-    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
-    311, 313, 317, 331, 337, 347, 349, 353, 359, 367, 373, 379, 383, 389, 397, 401, 409, 419, 421,
-    431, 433, 439, 443, 449, 457, 461, 463, 467, 479, 487, 491, 499, 503, 509, 521, 523, 541, 547,
-    557, 563, 569, 571, 577, 587, 593, 599, 601, 607, 613, 617, 619, 631, 641, 643, 647, 653, 659,
-    661, 673, 677, 683, 691, 701, 709, 719, 727, 733, 739, 743, 751, 757, 761, 769, 773, 787, 797,
-    809, 811, 821, 823, 827, 829, 839, 853, 857, 859, 863, 877, 881, 883, 887, 907, 911, 919, 929,
-    937, 941, 947, 953, 967, 971, 977, 983, 991, 997, 1009, 1013, 1019, 1021, 1031, 1033, 1039,
-    1049, 1051, 1061, 1063, 1069, 1087, 1091, 1093, 1097, 1103, 1109, 1117, 1123, 1129, 1151, 1153,
-    1163, 1171, 1181, 1187, 1193, 1201, 1213, 1217, 1223, 1229,
-];
-
-fn probable_prime_check(candidate: &BigUint) -> bool {
-    let config = PrimalityTestConfig::strict();
-    match is_prime(candidate, Some(config)) {
-        Primality::Yes => true,
-        Primality::No => false,
-        Primality::Probable(_) =>{
-            true
+const USAGE: &str = "\
+usage: large-primes <command> [options]
+
+commands:
+  generate              generate primes (default: one proven 100-digit prime)
+  test <n>              check n against the verification policy
+  prove <n>             print a primality certificate for n
+  next <n>              the smallest prime above n
+  bench                 time prime generation at several sizes
+  verify <file> [n]     re-check a stored certificate
+
+options:
+  --digits <d>          primes of exactly d decimal digits
+  --bits <b>            primes of exactly b bits
+  --count <k>           how many primes to generate, or runs per size to bench
+  --kind <kind>         random, safe, sophie-germain or strong
+  --format <format>     dec or hex
+  --threads <t>         1 for a sequential search, 0 for the global pool
+  --seed <s>            reproducible output from a ChaCha20 seed (not for keys)
+  --policy <policy>     proven, bpsw, fips or mr:<rounds>
+  --backend <backend>   auto, ecpp or pari (for --policy proven and prove)";
+
+/// Sizes `bench` times when no size is given.
+const BENCH_DIGITS: [usize; 7] = [10, 50, 100, 300, 800, 1000, 1500];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    if command == "verify" {
+        return verify(&args[1..]);
+    }
+    let (command, options) = match Command::parse(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let result = match command {
+        Command::Generate => match options.seed {
+            Some(seed) => generate(&mut ChaCha20Rng::seed_from_u64(seed), &options),
+            None => generate(&mut OsRng, &options),
+        },
+        Command::Test(n) => test(&n, &options),
+        Command::Prove(n) => prove(&n, &options),
+        Command::Next(n) => next(&n, &options),
+        Command::Bench => match options.seed {
+            Some(seed) => bench(&mut ChaCha20Rng::seed_from_u64(seed), &options),
+            None => bench(&mut OsRng, &options),
         },
+        Command::Help => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn deterministic_prime_check(candidate: &BigUint) -> bool {
-    let script_content = format!("default(parisizemax, 12000000000) \nprint(isprime({}));\nquit;", candidate);
-    let script_path = Path::new("temp_prime_check.gp");
-    
-    let _ = fs::write(script_path, script_content);
-
-    let output = Command::new("gp")
-        .arg("-q")
-        .arg(script_path)
-        .output()
-        .expect("Failed to run PARI/GP");
+/// Every command but `verify`, which reads its own arguments.
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Generate,
+    Test(BigUint),
+    Prove(BigUint),
+    Next(BigUint),
+    Bench,
+    Help,
+}
 
-    let _ = fs::remove_file(script_path);
+impl Command {
+    /// Parses a command line without the program name.
+    fn parse(args: &[String]) -> Result<(Command, Options), String> {
+        let options = Options::parse(&args[1..])?;
+        let command = match args[0].as_str() {
+            "generate" => options.no_arguments().map(|()| Command::Generate)?,
+            "test" => Command::Test(options.number()?),
+            "prove" => Command::Prove(options.number()?),
+            "next" => Command::Next(options.number()?),
+            "bench" => options.no_arguments().map(|()| Command::Bench)?,
+            "help" | "--help" | "-h" => Command::Help,
+            command => return Err(format!("unknown command `{}`", command)),
+        };
+        Ok((command, options))
+    }
+}
 
-    // println!("{}", String::from_utf8_lossy(&output.stdout));
-    // println!("Stderr: {}", String::from_utf8_lossy(&output.stderr));
-    
-    String::from_utf8_lossy(&output.stdout).trim() == "1"
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Random,
+    Safe,
+    SophieGermain,
+    Strong,
 }
 
-fn generate_prime_candidate(digits: usize) -> BigUint {
-    let lower = BigUint::from(10u32).pow(digits as u32 - 1);
-    let upper = BigUint::from(10u32).pow(digits as u32);
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Decimal,
+    Hex,
+}
 
-    let mut rng = thread_rng();
+/// Everything after the command name.
+struct Options {
+    size: Option<Size>,
+    count: usize,
+    kind: Kind,
+    format: Format,
+    parallelism: Parallelism,
+    seed: Option<u64>,
+    verification: VerificationPolicy,
+    arguments: Vec<String>,
+}
 
-    loop {
-        let num = rng.gen_biguint_range(&lower, &upper);
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            size: None,
+            count: 1,
+            kind: Kind::Random,
+            format: Format::Decimal,
+            parallelism: Parallelism::Auto,
+            seed: None,
+            verification: VerificationPolicy::default(),
+            arguments: Vec::new(),
+        };
+        let mut backend = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("`{}` needs a value", arg));
+            match arg.as_str() {
+                "--digits" => options.size = Some(Size::Digits(parse(arg, value()?)?)),
+                "--bits" => {
+                    options.size = Some(Size::Bits { bits: parse(arg, value()?)?, top_bits: 1 })
+                }
+                "--count" => options.count = parse(arg, value()?)?,
+                "--kind" => {
+                    options.kind = match value()?.as_str() {
+                        "random" => Kind::Random,
+                        "safe" => Kind::Safe,
+                        "sophie-germain" => Kind::SophieGermain,
+                        "strong" => Kind::Strong,
+                        kind => return Err(format!("unknown kind `{}`", kind)),
+                    }
+                }
+                "--format" => {
+                    options.format = match value()?.as_str() {
+                        "dec" => Format::Decimal,
+                        "hex" => Format::Hex,
+                        format => return Err(format!("unknown format `{}`", format)),
+                    }
+                }
+                "--threads" => {
+                    options.parallelism = match parse(arg, value()?)? {
+                        1 => Parallelism::Sequential,
+                        threads => Parallelism::Threads(threads),
+                    }
+                }
+                "--seed" => options.seed = Some(parse(arg, value()?)?),
+                "--policy" => {
+                    let policy = value()?;
+                    options.verification = match policy.as_str() {
+                        "proven" => VerificationPolicy::default(),
+                        "bpsw" => VerificationPolicy::Bpsw,
+                        "fips" => VerificationPolicy::Fips186_5,
+                        _ => match policy.strip_prefix("mr:") {
+                            Some(rounds) => match parse(arg, rounds)? {
+                                0 => return Err("`mr:` needs at least one round".to_string()),
+                                rounds => VerificationPolicy::ProbableOnly { rounds },
+                            },
+                            None => return Err(format!("unknown policy `{}`", policy)),
+                        },
+                    }
+                }
+                "--backend" => {
+                    backend = Some(match value()?.as_str() {
+                        "auto" => ProofBackend::Auto,
+                        "ecpp" => ProofBackend::Ecpp,
+                        "pari" => ProofBackend::Pari { timeout: None },
+                        backend => return Err(format!("unknown backend `{}`", backend)),
+                    })
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+                _ => options.arguments.push(arg.clone()),
+            }
+        }
+        if let Some(backend) = backend {
+            let VerificationPolicy::Proven { backend: proof } = &mut options.verification else {
+                return Err("`--backend` needs `--policy proven`".to_string());
+            };
+            *proof = backend;
+        }
+        if let Some(size) = options.size
+            && !size.is_valid()
+        {
+            return Err(format!("{} has no primes", describe(size)));
+        }
+        Ok(options)
+    }
 
-        if &num % 2u32 == BigUint::zero() {
-            let odd_num = num + BigUint::one();
+    fn no_arguments(&self) -> Result<(), String> {
+        match self.arguments.first() {
+            Some(arg) => Err(format!("unexpected argument `{}`", arg)),
+            None => Ok(()),
+        }
+    }
 
-            if odd_num < upper {
-                return odd_num;
-            } else {
-                continue;
-            }
+    /// The single number a command works on.
+    fn number(&self) -> Result<BigUint, String> {
+        match self.arguments.as_slice() {
+            [n] => n.parse().map_err(|_| format!("`{}` is not a number", n)),
+            _ => Err("expected one number".to_string()),
         }
+    }
 
-        return num;
+    /// A generator drawing from `rng`, with the size, threads and policy
+    /// applied.
+    fn generator<'a, R: RngCore + CryptoRng>(
+        &self,
+        rng: &'a mut R,
+        size: Size,
+    ) -> PrimeGenerator<&'a mut R> {
+        PrimeGenerator::new()
+            .rng(rng)
+            .size_of(size)
+            .parallelism(self.parallelism)
+            .verification(self.verification)
     }
-}
 
-fn sieve_check(candidate: &BigUint) -> bool {
-    for &p in SMALL_PRIMES.iter().skip(1) {
-        let bp = BigUint::from_u32(p).unwrap();
-        if candidate % &bp == BigUint::zero() && candidate != &bp {
-            return false;
+    fn print(&self, n: &BigUint) {
+        match self.format {
+            Format::Decimal => println!("{}", n),
+            Format::Hex => println!("{:#x}", n),
         }
     }
-    true
 }
 
-fn gen_rand_large_prime(digits: usize) -> BigUint {
-    let parallel_threshhold = 50;
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for `{}`", value, flag))
+}
 
-    if digits >= parallel_threshhold {
-        gen_rand_large_prime_parallel(digits)
-    } else {
-        gen_rand_large_prime_sequential(digits)
+fn describe(size: Size) -> String {
+    match size {
+        Size::Digits(digits) => format!("{} digits", digits),
+        Size::Bits { bits, .. } => format!("{} bits", bits),
     }
 }
 
-fn gen_rand_large_prime_sequential(digits: usize) -> BigUint {
-    loop {
-        let candidate = generate_prime_candidate(digits);
-
-        if !sieve_check(&candidate) {
-            continue;
+/// `generate`: `--count` primes of `--kind`. Random primes come from one
+/// stream, so they are distinct.
+fn generate<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    options: &Options,
+) -> Result<ExitCode, PrimeError> {
+    let size = options.size.unwrap_or(Size::Digits(100));
+    let error = match (options.kind, options.parallelism) {
+        (Kind::Random, Parallelism::Sequential) => {
+            let mut stream = options.generator(rng, size).stream()?;
+            stream.by_ref().take(options.count).for_each(|p| options.print(&p));
+            stream.error().cloned()
         }
-
-        if probable_prime_check(&candidate) {
-            if deterministic_prime_check(&candidate) {
-                return candidate;
+        (Kind::Random, _) => {
+            let mut stream = options.generator(rng, size).par_stream()?;
+            stream.by_ref().take(options.count).for_each(|p| options.print(&p));
+            stream.error().cloned()
+        }
+        _ => {
+            for _ in 0..options.count {
+                options.print(&generate_one(rng, options, size)?);
             }
+            None
         }
+    };
+    error.map_or(Ok(ExitCode::SUCCESS), Err)
+}
+
+fn generate_one<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    options: &Options,
+    size: Size,
+) -> Result<BigUint, PrimeError> {
+    match options.kind {
+        Kind::Random => options.generator(rng, size).generate(),
+        Kind::Safe => options.generator(rng, size).generate_safe(),
+        Kind::SophieGermain => options.generator(rng, size).generate_sophie_germain(),
+        Kind::Strong => options.generator(rng, size).generate_strong().map(|strong| strong.p),
     }
 }
 
-fn gen_rand_large_prime_parallel(digits: usize) -> BigUint {
-    let cpus = num_cpus::get();
-    let num_candidates = cpus * 4;
-    let found = Arc::new(AtomicBool::new(false));
+/// `test <n>`: exits with failure if `n` does not pass the policy.
+fn test(n: &BigUint, options: &Options) -> Result<ExitCode, PrimeError> {
+    let prime = options.verification.verify(n)?;
+    let verdict = match options.verification {
+        _ if !prime => "composite",
+        VerificationPolicy::Proven { .. } => "prime",
+        _ => "probably prime",
+    };
+    println!("{} is {}", n, verdict);
+    Ok(if prime { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+/// `prove <n>`: prints a certificate that `verify` accepts, from the native
+/// prover or, under `--backend pari`, from PARI/GP's `primecert` (for `n`
+/// above 64 bits).
+fn prove(n: &BigUint, options: &Options) -> Result<ExitCode, PrimeError> {
+    let backend = match options.verification {
+        VerificationPolicy::Proven { backend } => backend,
+        _ => ProofBackend::default(),
+    };
+    let start = Instant::now();
+    let cert = match backend.resolve() {
+        ProofBackend::Pari { timeout } if n.bits() > 64 => primecert(n, timeout)?,
+        _ => certify(n),
+    };
+    match cert {
+        Some(cert) => {
+            print!("{}", cert);
+            eprintln!("proven in {:.2?}", start.elapsed());
+            Ok(ExitCode::SUCCESS)
+        }
+        None => {
+            eprintln!("{} is not prime", n);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
 
+/// Asks PARI/GP for an ECPP certificate of `n`, `None` if `n` is composite.
+/// The certificate is parsed and checked before it is trusted.
+fn primecert(n: &BigUint, timeout: Option<Duration>) -> Result<Option<Certificate>, PrimeError> {
+    let expr = format!("iferr(primecert({}), e, \"error\")", n);
+    let output = GpSession::spawn()?.eval(&expr, timeout)?;
+    if output.trim() == "0" {
+        return Ok(None);
+    }
+    parse_certificate(&output)
+        .ok()
+        .filter(|cert| verify_certificate(n, cert).is_ok())
+        .map(Some)
+        .ok_or(PrimeError::BackendOutputUnparseable(output))
+}
+
+/// `next <n>`: the smallest prime above `n` that passes the policy. Under
+/// `--threads`, that many sieve survivors are checked at once.
+fn next(n: &BigUint, options: &Options) -> Result<ExitCode, PrimeError> {
+    let two = BigUint::from(2u32);
+    let mut candidate = n + 1u32;
+    if candidate <= two {
+        options.print(&two);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if !candidate.bit(0) {
+        candidate += 1u32;
+    }
+    let (pool, batch) = match options.parallelism {
+        Parallelism::Auto | Parallelism::Sequential => (None, 1),
+        Parallelism::Threads(0) => (None, rayon::current_num_threads()),
+        Parallelism::Threads(threads) => {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build();
+            (Some(pool.expect("failed to build rayon thread pool")), threads)
+        }
+    };
     loop {
-        let candidates: Vec<BigUint> = (0..num_candidates)
-            .into_par_iter()
-            .map(|_| generate_prime_candidate(digits))
-            .collect();
-
-        let sieved_candidates: Vec<BigUint> = candidates
-            .into_par_iter()
-            .filter(|c| sieve_check(c))
-            .collect();
-
-        let found_arc = Arc::clone(&found);
-        let prime_result = sieved_candidates.par_iter().find_map_first(|c| {
-            if found_arc.load(Ordering::Relaxed) {
-                return None;
+        let mut survivors = Vec::with_capacity(batch);
+        while survivors.len() < batch {
+            if sieve_check(&candidate) {
+                survivors.push(candidate.clone());
             }
+            candidate += 2u32;
+        }
+        let check = || {
+            let verify = |c| options.verification.verify(c);
+            survivors.par_iter().map(verify).collect::<Vec<_>>()
+        };
+        let verdicts = match &pool {
+            Some(pool) => pool.install(check),
+            None if batch == 1 => vec![options.verification.verify(&survivors[0])],
+            None => check(),
+        };
+        for (survivor, prime) in survivors.iter().zip(verdicts) {
+            if prime? {
+                options.print(survivor);
+                return Ok(ExitCode::SUCCESS);
+            }
+        }
+    }
+}
 
-            if probable_prime_check(c) {
-                if deterministic_prime_check(c) {
-                    found_arc.store(true, Ordering::Relaxed);
-                    Some(c.clone())
-                } else {
-                    None
-                }
-            } else {
-                None
+/// `bench`: `--count` runs at the given size, or at each of [`BENCH_DIGITS`].
+fn bench<R: RngCore + CryptoRng>(rng: &mut R, options: &Options) -> Result<ExitCode, PrimeError> {
+    let sizes = match options.size {
+        Some(size) => vec![size],
+        None => BENCH_DIGITS.map(Size::Digits).to_vec(),
+    };
+    for size in sizes {
+        let mut times = Vec::new();
+        for _ in 0..options.count.max(1) {
+            let start = Instant::now();
+            generate_one(rng, options, size)?;
+            times.push(start.elapsed());
+        }
+        let total: Duration = times.iter().sum();
+        println!(
+            "{:>12}: mean {:>10.2?}  min {:>10.2?}  max {:>10.2?}  ({} runs)",
+            describe(size),
+            total / times.len() as u32,
+            times.iter().min().unwrap(),
+            times.iter().max().unwrap(),
+            times.len(),
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `verify <certificate-file> [n]`: re-checks a stored certificate, for `n`
+/// if given and otherwise for the number the certificate names.
+fn verify(args: &[String]) -> ExitCode {
+    let Some(path) = args.first() else {
+        eprintln!("usage: large-primes verify <certificate-file> [n]");
+        return ExitCode::from(2);
+    };
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::from(2);
+        }
+    };
+    let cert = match parse_certificate(&text) {
+        Ok(cert) => cert,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let n = match args.get(1) {
+        Some(n) => match n.parse::<BigUint>() {
+            Ok(n) => n,
+            Err(_) => {
+                eprintln!("`{}` is not a number", n);
+                return ExitCode::from(2);
             }
-        });
+        },
+        None => cert.n.clone(),
+    };
 
-        if let Some(prime) = prime_result {
-            found.store(false, Ordering::Relaxed);
-            return prime.clone();
+    let start = Instant::now();
+    match verify_certificate(&n, &cert) {
+        Ok(()) => {
+            println!("{} is prime ({} steps verified in {:.2?})", n, cert.steps.len(), start.elapsed());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            ExitCode::FAILURE
         }
-        found.store(false, Ordering::Relaxed);
     }
 }
 
-fn main() {
-    let digits_arr: Vec<usize> = vec![10, 50, 100, 300, 800, 1000, 1500];
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for digits in digits_arr {
+    fn parse_line(line: &str) -> Result<(Command, Options), String> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        Command::parse(&args)
+    }
 
-        let start = Instant::now();
-        let test = gen_rand_large_prime(digits);
-        let duration = start.elapsed();
-        println!("----------------------------------------");
-        println!("{} digit prime:", digits);
-        println!("{}", test);
-        println!("Time elapsed: {:.2?}", duration);
-        println!("----------------------------------------");
+    fn error(line: &str) -> String {
+        match parse_line(line) {
+            Ok((command, _)) => panic!("`{}` parsed as {:?}", line, command),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn subcommands() {
+        let command = |line| parse_line(line).unwrap().0;
+        assert_eq!(command("generate --bits 256"), Command::Generate);
+        assert_eq!(command("test 97"), Command::Test(BigUint::from(97u32)));
+        assert_eq!(command("prove 101"), Command::Prove(BigUint::from(101u32)));
+        assert_eq!(command("next 1000"), Command::Next(BigUint::from(1000u32)));
+        assert_eq!(command("bench --digits 40"), Command::Bench);
+        assert_eq!(command("--help"), Command::Help);
+
+        assert_eq!(error("frobnicate"), "unknown command `frobnicate`");
+        assert_eq!(error("generate 97"), "unexpected argument `97`");
+        assert_eq!(error("test"), "expected one number");
+        assert_eq!(error("test 3 5"), "expected one number");
+        assert_eq!(error("next ten"), "`ten` is not a number");
+        assert_eq!(error("test 97 --verbose"), "unknown option `--verbose`");
+    }
+
+    #[test]
+    fn threads() {
+        let parallelism = |line| parse_line(line).unwrap().1.parallelism;
+        assert_eq!(parallelism("generate"), Parallelism::Auto);
+        assert_eq!(parallelism("generate --threads 1"), Parallelism::Sequential);
+        assert_eq!(parallelism("generate --threads 0"), Parallelism::Threads(0));
+        assert_eq!(parallelism("generate --threads 4"), Parallelism::Threads(4));
+        assert!(error("generate --threads many").contains("--threads"));
+        assert_eq!(error("generate --threads"), "`--threads` needs a value");
+    }
+
+    #[test]
+    fn seed() {
+        let seed = |line| parse_line(line).unwrap().1.seed;
+        assert_eq!(seed("generate"), None);
+        assert_eq!(seed("generate --seed 42"), Some(42));
+        assert!(error("generate --seed -1").contains("--seed"));
+        assert_eq!(error("bench --seed"), "`--seed` needs a value");
+    }
 
+    #[test]
+    fn backend() {
+        let verification = |line| parse_line(line).unwrap().1.verification;
+        let proven = |backend| VerificationPolicy::Proven { backend };
+        assert_eq!(verification("prove 101"), proven(ProofBackend::Auto));
+        assert_eq!(verification("prove 101 --backend ecpp"), proven(ProofBackend::Ecpp));
+        assert_eq!(
+            verification("prove 101 --backend pari --policy proven"),
+            proven(ProofBackend::Pari { timeout: None })
+        );
+        assert_eq!(error("prove 101 --backend magma"), "unknown backend `magma`");
+        assert_eq!(
+            error("generate --policy bpsw --backend ecpp"),
+            "`--backend` needs `--policy proven`"
+        );
     }
 }
@@ -0,0 +1,166 @@
+//! Long-lived PARI/GP sessions.
+//!
+//! Each thread that asks for a proof keeps its own `gp` process and talks to
+//! it over stdin/stdout, so concurrent checks neither share a process nor
+//! touch the filesystem. A session that crashes is restarted on the next
+//! call; a session that exceeds its timeout, or whose search is cancelled
+//! mid-call, is killed.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+
+use crate::cancel::Stop;
+use crate::error::PrimeError;
+
+/// Maximum PARI stack size requested at session start, in bytes.
+pub const PARISIZEMAX: u64 = 12_000_000_000;
+
+/// How often a pending call checks whether its search was cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// One running `gp -q` process.
+pub struct GpSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Receiver<String>,
+}
+
+impl GpSession {
+    pub fn spawn() -> Result<Self, PrimeError> {
+        let mut child = Command::new("gp")
+            .args(["-q", "-f"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| PrimeError::BackendMissing)?;
+        let stdin = child.stdin.take().ok_or(PrimeError::BackendCrashed)?;
+        let stdout = child.stdout.take().ok_or(PrimeError::BackendCrashed)?;
+
+        // A reader thread turns stdout into a channel so calls can time out.
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut session = GpSession { child, stdin, stdout: rx };
+        session.send(&format!("default(parisizemax, {});", PARISIZEMAX))?;
+        Ok(session)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), PrimeError> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_: io::Error| PrimeError::BackendCrashed)
+    }
+
+    /// Evaluates `expr` and returns the single line it prints.
+    pub fn eval(&mut self, expr: &str, timeout: Option<Duration>) -> Result<String, PrimeError> {
+        self.eval_until(expr, timeout, &Stop::default())
+    }
+
+    /// Like [`eval`](Self::eval), giving up as soon as `stop` says so.
+    pub(crate) fn eval_until(
+        &mut self,
+        expr: &str,
+        timeout: Option<Duration>,
+        stop: &Stop,
+    ) -> Result<String, PrimeError> {
+        self.send(&format!("print({})", expr))?;
+        let expires = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let wait = match expires {
+                Some(expires) => expires.saturating_duration_since(Instant::now()).min(POLL_INTERVAL),
+                None => POLL_INTERVAL,
+            };
+            match self.stdout.recv_timeout(wait) {
+                Ok(line) => return Ok(line),
+                Err(RecvTimeoutError::Disconnected) => return Err(PrimeError::BackendCrashed),
+                Err(RecvTimeoutError::Timeout) => {
+                    stop.check()?;
+                    if expires.is_some_and(|expires| Instant::now() >= expires) {
+                        return Err(PrimeError::BackendTimeout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs `isprime(n)`. PARI errors (e.g. stack overflow) are reported as
+    /// [`PrimeError::BackendOutputUnparseable`] rather than left to hang the
+    /// session.
+    pub fn isprime(&mut self, n: &BigUint, timeout: Option<Duration>) -> Result<bool, PrimeError> {
+        self.isprime_until(n, timeout, &Stop::default())
+    }
+
+    fn isprime_until(
+        &mut self,
+        n: &BigUint,
+        timeout: Option<Duration>,
+        stop: &Stop,
+    ) -> Result<bool, PrimeError> {
+        let expr = format!("iferr(isprime({}), e, \"error\")", n);
+        let output = self.eval_until(&expr, timeout, stop)?;
+        match output.trim() {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            other => Err(PrimeError::BackendOutputUnparseable(other.to_string())),
+        }
+    }
+}
+
+impl Drop for GpSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+thread_local! {
+    static SESSION: RefCell<Option<GpSession>> = const { RefCell::new(None) };
+}
+
+/// Runs `isprime(n)` on this thread's session, starting it if needed.
+///
+/// A session that crashed is restarted and the call retried once. Any
+/// failure drops the session so the next call starts from a clean process.
+pub fn isprime(n: &BigUint, timeout: Option<Duration>) -> Result<bool, PrimeError> {
+    isprime_until(n, timeout, &Stop::default())
+}
+
+/// Like [`isprime`], killing the session and failing with
+/// [`PrimeError::Cancelled`] or [`PrimeError::TimedOut`] once `stop` says so.
+pub(crate) fn isprime_until(
+    n: &BigUint,
+    timeout: Option<Duration>,
+    stop: &Stop,
+) -> Result<bool, PrimeError> {
+    SESSION.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        let mut retried = false;
+        loop {
+            if slot.is_none() {
+                *slot = Some(GpSession::spawn()?);
+            }
+            let result = slot.as_mut().unwrap().isprime_until(n, timeout, stop);
+            if result.is_err() {
+                *slot = None;
+            }
+            match result {
+                Err(PrimeError::BackendCrashed) if !retried => retried = true,
+                result => return result,
+            }
+        }
+    })
+}
@@ -0,0 +1,306 @@
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use num_prime::{nt_funcs::is_prime, Primality, PrimalityTestConfig};
+use num_bigint::{BigInt, RandBigInt};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+use crate::arith::{exact_sqrt, kronecker, to_residue};
+use crate::cancel::Stop;
+use crate::ecpp;
+use crate::error::PrimeError;
+use crate::pari;
+use crate::sieve::SMALL_PRIMES;
+
+/// Runs `num_prime`'s strict probable-prime test.
+///
+/// `Primality::Probable` is treated as prime; use
+/// [`probable_prime_status`] to tell it apart from `Primality::Yes`.
+pub fn probable_prime_check(candidate: &BigUint) -> bool {
+    match probable_prime_status(candidate) {
+        Primality::Yes => true,
+        Primality::No => false,
+        Primality::Probable(_) =>{
+            true
+        },
+    }
+}
+
+/// The raw `num_prime` verdict behind [`probable_prime_check`]: `Yes` only
+/// for inputs small enough to be decided deterministically, `Probable` with
+/// an error bound otherwise.
+pub fn probable_prime_status(candidate: &BigUint) -> Primality {
+    let config = PrimalityTestConfig::strict();
+    is_prime(candidate, Some(config))
+}
+
+/// The probable-prime test that screens candidates before any proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProbableTest {
+    /// [`probable_prime_check`]: `num_prime`'s strict configuration.
+    #[default]
+    Strict,
+    /// The in-crate [`bpsw_check`].
+    Bpsw,
+}
+
+impl ProbableTest {
+    /// Returns `true` if `candidate` passes this test.
+    pub fn check(&self, candidate: &BigUint) -> bool {
+        match self {
+            ProbableTest::Strict => probable_prime_check(candidate),
+            ProbableTest::Bpsw => bpsw_check(candidate),
+        }
+    }
+}
+
+/// Which prover backs a "deterministic" primality result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProofBackend {
+    /// PARI/GP if `gp` is on `PATH`, the native prover otherwise.
+    #[default]
+    Auto,
+    /// The in-crate elliptic curve prover, see [`crate::ecpp`].
+    Ecpp,
+    /// PARI/GP's `isprime` on a persistent session, see [`crate::pari`].
+    /// A call that exceeds `timeout` fails with [`PrimeError::BackendTimeout`].
+    Pari { timeout: Option<Duration> },
+}
+
+impl ProofBackend {
+    /// Resolves [`ProofBackend::Auto`] to a concrete backend.
+    pub fn resolve(&self) -> ProofBackend {
+        match self {
+            ProofBackend::Auto if pari_available() => ProofBackend::Pari { timeout: None },
+            ProofBackend::Auto => ProofBackend::Ecpp,
+            backend => *backend,
+        }
+    }
+
+    /// Returns `Ok(true)` if `candidate` is proven prime by this backend.
+    pub fn prove(&self, candidate: &BigUint) -> Result<bool, PrimeError> {
+        self.prove_until(candidate, &Stop::default())
+    }
+
+    /// Like [`prove`](Self::prove), abandoning the proof once `stop` says so.
+    pub(crate) fn prove_until(&self, candidate: &BigUint, stop: &Stop) -> Result<bool, PrimeError> {
+        match self.resolve() {
+            ProofBackend::Pari { timeout } => pari::isprime_until(candidate, timeout, stop),
+            _ => Ok(ecpp::prove_until(candidate, stop)?.is_some()),
+        }
+    }
+}
+
+/// Returns `true` if a `gp` binary can be started. Checked once per process.
+pub fn pari_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("gp")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    })
+}
+
+/// Proves primality with PARI/GP's `isprime`, on this thread's persistent
+/// `gp` session (see [`crate::pari`]).
+///
+/// Requires a `gp` binary on `PATH`; fails with
+/// [`PrimeError::BackendMissing`] otherwise.
+pub fn deterministic_prime_check(candidate: &BigUint) -> Result<bool, PrimeError> {
+    pari::isprime(candidate, None)
+}
+
+/// Strong probable-prime (Miller–Rabin) test of an odd `n > 2` to `base`.
+pub fn is_strong_probable_prime(n: &BigUint, base: &BigUint) -> bool {
+    let one = BigUint::one();
+    let n_minus_1 = n - &one;
+    let s = n_minus_1.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_1 >> s;
+    let mut x = base.modpow(&d, n);
+    if x == one || x == n_minus_1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = &x * &x % n;
+        if x == n_minus_1 {
+            return true;
+        }
+        if x == one {
+            return false;
+        }
+    }
+    false
+}
+
+/// Miller–Rabin test with `rounds` uniformly random bases in `[2, n - 2]`,
+/// drawn from [`OsRng`].
+///
+/// A composite passes with probability at most `4^-rounds`, and far less for
+/// random large candidates.
+pub fn miller_rabin_check(n: &BigUint, rounds: usize) -> bool {
+    miller_rabin_check_with_rng(&mut OsRng, n, rounds)
+}
+
+/// Like [`miller_rabin_check`], drawing the bases from `rng`.
+pub fn miller_rabin_check_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    n: &BigUint,
+    rounds: usize,
+) -> bool {
+    if let Some(small) = n.to_u32() {
+        if small < 2 {
+            return false;
+        }
+        if SMALL_PRIMES.binary_search(&small).is_ok() {
+            return true;
+        }
+    }
+    if SMALL_PRIMES.iter().any(|&p| (n % p).is_zero()) {
+        return false;
+    }
+    let upper = n - 1u32;
+    (0..rounds).all(|_| {
+        let base = rng.gen_biguint_range(&BigUint::from(2u32), &upper);
+        is_strong_probable_prime(n, &base)
+    })
+}
+
+/// Miller–Rabin rounds required by FIPS 186-5 (Table B.1, "M-R tests
+/// only" column) for a prime of `bits` bits: 5 below 1536 bits (the row for
+/// the 1024-bit primes of 2048-bit RSA), 4 from 1536 bits up (the row for
+/// 3072-bit RSA). Sizes between rows get the stricter one. Primes of 512 bits
+/// or less get the 7 rounds FIPS 186-4 Table C.3 asks of 512-bit primes.
+pub fn fips186_5_rounds(bits: u64) -> usize {
+    match bits {
+        0..=512 => 7,
+        513..=1535 => 5,
+        _ => 4,
+    }
+}
+
+/// Baillie–PSW test: a strong base-2 Miller–Rabin test followed by a strong
+/// Lucas test with Selfridge's parameters.
+///
+/// No composite passing both is known; it is deterministic below `2^64`.
+pub fn bpsw_check(n: &BigUint) -> bool {
+    if let Some(small) = n.to_u32() {
+        if small < 2 {
+            return false;
+        }
+        if SMALL_PRIMES.binary_search(&small).is_ok() {
+            return true;
+        }
+    }
+    if SMALL_PRIMES.iter().any(|&p| (n % p).is_zero()) {
+        return false;
+    }
+    is_strong_probable_prime(n, &BigUint::from(2u32)) && is_strong_lucas_probable_prime(n)
+}
+
+/// Strong Lucas probable-prime test of an odd `n` with no prime factor below
+/// 1230, using Selfridge's method A: `D` is the first of `5, -7, 9, -11, ...`
+/// with `(D / n) = -1`, `P = 1` and `Q = (1 - D) / 4`.
+pub fn is_strong_lucas_probable_prime(n: &BigUint) -> bool {
+    if exact_sqrt(n).is_some() {
+        return false;
+    }
+    let mut d = 5i64;
+    loop {
+        match kronecker(d, n) {
+            -1 => break,
+            0 if BigUint::from(d.unsigned_abs()) != *n => return false,
+            _ => {}
+        }
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+    let d_mod = to_residue(&BigInt::from(d), n);
+    let q = to_residue(&BigInt::from((1 - d) / 4), n);
+
+    let n_plus_1 = n + 1u32;
+    let s = n_plus_1.trailing_zeros().unwrap_or(0);
+    let k = &n_plus_1 >> s;
+
+    let half = |x: BigUint| if x.is_odd() { (x + n) >> 1 } else { x >> 1 };
+    let sub = |a: BigUint, b: &BigUint| if &a >= b { a - b } else { a + n - b };
+
+    // U_1 = 1, V_1 = P = 1.
+    let (mut u, mut v, mut q_k) = (BigUint::one(), BigUint::one(), q.clone());
+    for bit in (0..k.bits() - 1).rev() {
+        u = &u * &v % n;
+        v = sub(&v * &v % n, &(&q_k * 2u32 % n));
+        q_k = &q_k * &q_k % n;
+        if k.bit(bit) {
+            let u_next = half((&u + &v) % n);
+            v = half((&d_mod * &u + &v) % n);
+            u = u_next;
+            q_k = &q_k * &q % n;
+        }
+    }
+
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        v = sub(&v * &v % n, &(&q_k * 2u32 % n));
+        if v.is_zero() {
+            return true;
+        }
+        q_k = &q_k * &q_k % n;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_prime::nt_funcs::is_prime64;
+
+    #[test]
+    fn fips186_5_rounds_follow_table_b1() {
+        assert_eq!(fips186_5_rounds(512), 7);
+        assert_eq!(fips186_5_rounds(1024), 5);
+        assert_eq!(fips186_5_rounds(1025), 5);
+        assert_eq!(fips186_5_rounds(1535), 5);
+        assert_eq!(fips186_5_rounds(1536), 4);
+        assert_eq!(fips186_5_rounds(2048), 4);
+    }
+
+    #[test]
+    fn strong_lucas_pseudoprimes_pass_the_lucas_test() {
+        // OEIS A217255.
+        for n in [5459u32, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309, 58519] {
+            assert!(!is_prime64(n as u64));
+            assert!(is_strong_lucas_probable_prime(&BigUint::from(n)), "{}", n);
+        }
+    }
+
+    #[test]
+    fn strong_base_2_pseudoprimes_fail_bpsw() {
+        // OEIS A001262, and the largest base-2 strong pseudoprime used to
+        // bound deterministic bases.
+        let pseudoprimes = [2047u64, 3277, 4033, 4681, 8321, 15841, 29341, 3825123056546413051];
+        for n in pseudoprimes {
+            let n = BigUint::from(n);
+            assert!(is_strong_probable_prime(&n, &BigUint::from(2u32)), "{}", n);
+            assert!(!bpsw_check(&n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn bpsw_agrees_with_is_prime64() {
+        for n in 0u64..200_000 {
+            assert_eq!(bpsw_check(&BigUint::from(n)), is_prime64(n), "{}", n);
+        }
+        for n in (u64::MAX - 20_000)..=u64::MAX {
+            assert_eq!(bpsw_check(&BigUint::from(n)), is_prime64(n), "{}", n);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Trial division by many primes at once: a candidate is reduced modulo
+//! products of consecutive small primes and checked for a common factor with
+//! one GCD per product, instead of one remainder per prime.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::sieve::{odd_primes_below, trial_division};
+
+/// Each cached product is about this many times as long as the candidates it
+/// is used for: long enough that reducing it modulo a candidate is one long
+/// division, short enough that the first (smallest-prime) product rejects
+/// most composites on its own.
+const PRODUCT_SIZE_FACTOR: u64 = 8;
+
+/// Candidates up to this many bits may equal a product of small primes, so
+/// they are trial-divided instead.
+const SMALL_CANDIDATE_BITS: u64 = 32;
+
+/// Products of the odd primes below `limit`, split into consecutive runs of
+/// about `PRODUCT_SIZE_FACTOR * class_bits` bits each, smallest primes
+/// first. Built once per limit and size class (bit length rounded up to a
+/// power of two).
+pub fn prime_products(limit: u32, bits: u64) -> Arc<Vec<BigUint>> {
+    type Cache = Mutex<HashMap<(u32, u64), Arc<Vec<BigUint>>>>;
+    static CACHE: LazyLock<Cache> = LazyLock::new(Default::default);
+
+    let class = bits.max(64).next_power_of_two();
+    if let Some(products) = CACHE.lock().unwrap().get(&(limit, class)) {
+        return products.clone();
+    }
+
+    let target = PRODUCT_SIZE_FACTOR * class;
+    let mut products = Vec::new();
+    let mut run: Vec<BigUint> = Vec::new();
+    let mut run_bits = 0;
+    for &p in odd_primes_below(limit).iter() {
+        run.push(BigUint::from(p));
+        run_bits += 32 - p.leading_zeros() as u64;
+        if run_bits >= target {
+            products.push(product_tree(&run));
+            run.clear();
+            run_bits = 0;
+        }
+    }
+    if !run.is_empty() {
+        products.push(product_tree(&run));
+    }
+
+    let products = Arc::new(products);
+    CACHE.lock().unwrap().insert((limit, class), products.clone());
+    products
+}
+
+/// Product of `factors`, multiplied pairwise up a balanced tree so that the
+/// large multiplications are between operands of similar size.
+fn product_tree(factors: &[BigUint]) -> BigUint {
+    match factors {
+        [] => BigUint::one(),
+        [single] => single.clone(),
+        _ => {
+            let (left, right) = factors.split_at(factors.len() / 2);
+            product_tree(left) * product_tree(right)
+        }
+    }
+}
+
+/// Returns `false` if any odd prime below `limit` is a proper divisor of
+/// `candidate`; the same answer as trial division, via [`prime_products`].
+///
+/// The first product, holding the smallest primes, gets its own GCD since it
+/// rejects most composites. The remainders of the others are multiplied
+/// together modulo `candidate` and checked with a single GCD: a prime
+/// dividing `candidate` divides that product exactly when it divides one of
+/// the prime products.
+pub fn primorial_check(candidate: &BigUint, limit: u32) -> bool {
+    if candidate.bits() <= SMALL_CANDIDATE_BITS {
+        return trial_division(candidate, &odd_primes_below(limit));
+    }
+    let products = prime_products(limit, candidate.bits());
+    let Some((first, rest)) = products.split_first() else {
+        return true;
+    };
+    if !(first % candidate).gcd(candidate).is_one() {
+        return false;
+    }
+    let accumulated = rest
+        .iter()
+        .fold(BigUint::one(), |acc, product| acc * (product % candidate) % candidate);
+    accumulated.gcd(candidate).is_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::RandBigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    #[test]
+    fn primorial_check_agrees_with_trial_division() {
+        let rng = &mut ChaCha20Rng::seed_from_u64(2);
+        for limit in [1000, 1 << 16, 100_003] {
+            let primes = odd_primes_below(limit);
+            let largest = BigUint::from(*primes.last().unwrap());
+            for bits in [8, 32, 33, 64, 65, 128, 500, 2048] {
+                for _ in 0..40 {
+                    let n = rng.gen_biguint(bits) | BigUint::one();
+                    for n in [n.clone(), &n * &largest] {
+                        let expected = trial_division(&n, &primes);
+                        assert_eq!(primorial_check(&n, limit), expected, "{}", n);
+                    }
+                }
+            }
+            assert!(primorial_check(&largest, limit));
+        }
+    }
+}
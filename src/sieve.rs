@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use num_bigint::BigUint;
+use num_traits::{FromPrimitive, ToPrimitive, Zero};
+
+use crate::primorial::primorial_check;
+
+/// Number of entries in [`SMALL_PRIMES`].
+pub const SMALL_PRIME_COUNT: usize = 201;
+
+/// The first [`SMALL_PRIME_COUNT`] primes (2 to 1229), computed at compile time.
+pub const SMALL_PRIMES: [u32; SMALL_PRIME_COUNT] = first_primes();
+
+/// Trial-division limit used when none is configured: just past the last
+/// entry of [`SMALL_PRIMES`].
+pub const DEFAULT_TRIAL_DIVISION_LIMIT: u32 = SMALL_PRIMES[SMALL_PRIME_COUNT - 1] + 1;
+
+const fn first_primes<const N: usize>() -> [u32; N] {
+    let mut primes = [0u32; N];
+    let mut count = 0;
+    let mut n = 2;
+    while count < N {
+        let mut i = 0;
+        let mut is_prime = true;
+        while i < count && primes[i] * primes[i] <= n {
+            if n % primes[i] == 0 {
+                is_prime = false;
+                break;
+            }
+            i += 1;
+        }
+        if is_prime {
+            primes[count] = n;
+            count += 1;
+        }
+        n += 1;
+    }
+    primes
+}
+
+/// Trial-divides an odd candidate by the odd entries of [`SMALL_PRIMES`].
+///
+/// Returns `false` if any of them is a proper divisor.
+pub fn sieve_check(candidate: &BigUint) -> bool {
+    trial_division(candidate, &SMALL_PRIMES[1..])
+}
+
+/// Returns `false` if any of `primes` is a proper divisor of `candidate`.
+pub fn trial_division(candidate: &BigUint, primes: &[u32]) -> bool {
+    for &p in primes {
+        let bp = BigUint::from_u32(p).unwrap();
+        if candidate % &bp == BigUint::zero() && candidate != &bp {
+            return false;
+        }
+    }
+    true
+}
+
+/// Primes below this bound are sieved out of both halves of a safe prime.
+pub const SAFE_SIEVE_BOUND: u32 = 1 << 16;
+
+static SAFE_SIEVE_PRIMES: LazyLock<Arc<Vec<u32>>> =
+    LazyLock::new(|| odd_primes_below(SAFE_SIEVE_BOUND));
+
+/// Returns `false` if the odd prime below [`SAFE_SIEVE_BOUND`] divides `p` or
+/// `q = (p - 1) / 2`, i.e. if `p` is `0` or `1` modulo it.
+pub fn safe_sieve_check(p: &BigUint) -> bool {
+    safe_trial_division(p, &SAFE_SIEVE_PRIMES)
+}
+
+/// Returns `false` if any of the odd `primes` properly divides `p` or
+/// `(p - 1) / 2`.
+pub fn safe_trial_division(p: &BigUint, primes: &[u32]) -> bool {
+    let q = p >> 1;
+    for &r in primes {
+        let rem = (p % r).to_u32().unwrap();
+        if (rem == 0 && p != &BigUint::from(r)) || (rem == 1 && q != BigUint::from(r)) {
+            return false;
+        }
+    }
+    true
+}
+
+/// What a search sieves against: the odd primes below a limit, checked
+/// against the candidate alone or, for safe primes `p`, also against
+/// `(p - 1) / 2`.
+#[derive(Clone, Debug)]
+pub(crate) struct Sieve {
+    safe: bool,
+    limit: u32,
+    primes: Arc<Vec<u32>>,
+}
+
+impl Sieve {
+    /// Plain primes, trial-divided up to `limit` ([`SMALL_PRIMES`] if `None`).
+    pub(crate) fn prime(limit: Option<u32>) -> Self {
+        let limit = limit.unwrap_or(DEFAULT_TRIAL_DIVISION_LIMIT);
+        Sieve { safe: false, limit, primes: odd_primes_below(limit) }
+    }
+
+    /// Safe primes, trial-divided up to `limit` ([`SAFE_SIEVE_BOUND`] if `None`).
+    pub(crate) fn safe(limit: Option<u32>) -> Self {
+        let limit = limit.unwrap_or(SAFE_SIEVE_BOUND);
+        Sieve { safe: true, limit, primes: odd_primes_below(limit) }
+    }
+
+    pub(crate) fn is_safe(&self) -> bool {
+        self.safe
+    }
+
+    /// Primes below this are trial-divided.
+    pub(crate) fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Trial-divides `candidate`, switching to [`primorial_check`] for
+    /// long prime lists.
+    pub(crate) fn check(&self, candidate: &BigUint) -> bool {
+        if self.primes.len() >= PRIMORIAL_MIN_PRIMES && candidate.bits() > 64 {
+            let q = candidate >> 1;
+            return primorial_check(candidate, self.limit)
+                && (!self.safe || primorial_check(&q, self.limit));
+        }
+        if self.safe {
+            safe_trial_division(candidate, &self.primes)
+        } else {
+            trial_division(candidate, &self.primes)
+        }
+    }
+
+    /// The odd primes sieved against.
+    pub(crate) fn primes(&self) -> &[u32] {
+        &self.primes
+    }
+
+    /// Returns `false` if a candidate that is `rem` modulo a sieving prime
+    /// is rejected by it.
+    pub(crate) fn admits_residue(&self, rem: u32) -> bool {
+        if self.safe { rem > 1 } else { rem != 0 }
+    }
+}
+
+/// Prime lists at least this long are checked with [`primorial_check`]
+/// rather than one remainder per prime.
+pub const PRIMORIAL_MIN_PRIMES: usize = 4096;
+
+/// Smallest and largest sieving bounds picked by [`auto_sieve_bound`].
+pub const MIN_SIEVE_BOUND: u32 = 100_000;
+pub const MAX_SIEVE_BOUND: u32 = 10_000_000;
+
+/// Sieving bound for the windowed sieve: `10 * digits^2`, clamped to
+/// `[MIN_SIEVE_BOUND, MAX_SIEVE_BOUND]`. Larger candidates make each
+/// probable-prime test dearer, so sieving deeper pays off.
+pub fn auto_sieve_bound(digits: usize) -> u32 {
+    let bound = (digits as u64).saturating_mul(digits as u64).saturating_mul(10);
+    bound.clamp(MIN_SIEVE_BOUND as u64, MAX_SIEVE_BOUND as u64) as u32
+}
+
+/// The odd primes below `bound`, computed once per bound.
+pub fn odd_primes_below(bound: u32) -> Arc<Vec<u32>> {
+    static CACHE: LazyLock<Mutex<HashMap<u32, Arc<Vec<u32>>>>> = LazyLock::new(Default::default);
+    if let Some(primes) = CACHE.lock().unwrap().get(&bound) {
+        return primes.clone();
+    }
+    let limit = bound as usize;
+    let mut composite = vec![false; limit];
+    let mut primes = Vec::new();
+    for i in (3..limit).step_by(2) {
+        if !composite[i] {
+            primes.push(i as u32);
+            for j in (i * i..limit).step_by(2 * i) {
+                composite[j] = true;
+            }
+        }
+    }
+    let primes = Arc::new(primes);
+    CACHE.lock().unwrap().insert(bound, primes.clone());
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::RandBigInt;
+    use num_prime::nt_funcs::is_prime64;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    /// Odd primes below `limit`, found without the sieve.
+    fn reference_primes(limit: u32) -> Vec<u32> {
+        (3..limit).step_by(2).filter(|&p| is_prime64(p as u64)).collect()
+    }
+
+    /// Whether one of `primes` properly divides `n`.
+    fn divisible(n: &BigUint, primes: &[u32]) -> bool {
+        primes.iter().any(|&p| (n % p).is_zero() && *n != BigUint::from(p))
+    }
+
+    /// Odd candidates of `bits` bits: random ones, and ones with a factor
+    /// just below or above `limit`.
+    fn candidates(rng: &mut ChaCha20Rng, bits: u64, limit: u32) -> Vec<BigUint> {
+        let odd = |n: BigUint| n | BigUint::from(1u32);
+        let mut candidates: Vec<_> = (0..50).map(|_| odd(rng.gen_biguint(bits))).collect();
+        for p in [limit - 1, limit + 1].into_iter().filter(|&p| is_prime64(p as u64)) {
+            candidates.push(odd(rng.gen_biguint(bits.saturating_sub(32).max(1))) * p);
+        }
+        candidates.extend((0..20).map(|_| BigUint::from(rng.gen_range(1..2 * limit) | 1)));
+        candidates
+    }
+
+    #[test]
+    fn small_primes_match_the_sieve() {
+        assert_eq!(SMALL_PRIMES[0], 2);
+        assert_eq!(SMALL_PRIMES[1..], odd_primes_below(DEFAULT_TRIAL_DIVISION_LIMIT)[..]);
+        assert_eq!(*odd_primes_below(100_003), reference_primes(100_003));
+    }
+
+    #[test]
+    fn sieve_agrees_with_trial_division() {
+        let rng = &mut ChaCha20Rng::seed_from_u64(1);
+        for limit in [DEFAULT_TRIAL_DIVISION_LIMIT, 1 << 16] {
+            let primes = reference_primes(limit);
+            let (sieve, safe) = (Sieve::prime(Some(limit)), Sieve::safe(Some(limit)));
+            for bits in [16, 64, 65, 200, 1000] {
+                for p in candidates(rng, bits, limit) {
+                    assert_eq!(sieve.check(&p), !divisible(&p, &primes), "{} / {}", p, limit);
+                    let q = &p >> 1;
+                    let expected = !divisible(&p, &primes) && !divisible(&q, &primes);
+                    assert_eq!(safe.check(&p), expected, "{} / {}", p, limit);
+                }
+            }
+        }
+    }
+}
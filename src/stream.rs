@@ -0,0 +1,346 @@
+//! Endless streams of distinct primes.
+//!
+//! [`PrimeGenerator::stream`](crate::PrimeGenerator::stream) and
+//! [`PrimeGenerator::par_stream`](crate::PrimeGenerator::par_stream) turn a
+//! configured generator into an iterator that keeps one search running
+//! instead of starting a new one per prime: sieve tables and walk positions
+//! carry over, and the parallel stream keeps its workers busy between calls
+//! to `next`.
+//!
+//! A stream ends only on an error, which [`error`](PrimeStream::error) then
+//! reports: cancellation, a missed deadline, or
+//! [`PrimeError::AttemptsExhausted`] once a small space has no new primes
+//! left to give.
+//!
+//! To keep primes distinct, a stream over fewer than 2^64 candidates
+//! remembers every prime it returned, so its memory grows with each prime
+//! taken. Over larger spaces it remembers nothing, and repeats are merely
+//! unlikely: the chance of one among `n` primes drawn from a space holding
+//! `P` primes is about `n^2 / 2P`, or 2^-21 for a million primes out of 2^60.
+//!
+//! ```no_run
+//! use large_primes::{PrimeGenerator, VerificationPolicy};
+//!
+//! let primes: Vec<_> = PrimeGenerator::new()
+//!     .bits(512)
+//!     .verification(VerificationPolicy::Bpsw)
+//!     .par_stream()?
+//!     .take(100)
+//!     .collect();
+//! assert_eq!(primes.len(), 100);
+//! # Ok::<(), large_primes::PrimeError>(())
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use num_bigint::BigUint;
+
+use crate::cancel::Stop;
+use crate::constraint::Space;
+use crate::error::PrimeError;
+use crate::generator::{Candidates, Slots, VerificationPolicy, slot_rng, test_rng};
+use crate::primality::ProbableTest;
+
+/// Spaces with at least this many bits' worth of candidates don't track
+/// returned primes.
+const DISTINCT_BELOW_BITS: u64 = 64;
+
+/// Keeps a stream's primes distinct and notices when a space runs dry.
+struct Distinct {
+    seen: Option<HashSet<BigUint>>,
+    /// Slots since the last new prime.
+    idle: u64,
+    limit: u64,
+}
+
+impl Distinct {
+    fn new(space: &Space) -> Self {
+        Distinct {
+            seen: (space.count().bits() < DISTINCT_BELOW_BITS).then(HashSet::new),
+            idle: 0,
+            limit: space.attempts(),
+        }
+    }
+
+    /// Takes the outcome of one slot, `None` for a rejected candidate, and
+    /// returns the prime if it has not been returned before. Fails once
+    /// [`Space::attempts`] slots in a row brought nothing new.
+    fn admit(&mut self, prime: Option<BigUint>) -> Result<Option<BigUint>, PrimeError> {
+        if let Some(prime) = prime
+            && self.seen.as_mut().is_none_or(|seen| seen.insert(prime.clone()))
+        {
+            self.idle = 0;
+            return Ok(Some(prime));
+        }
+        self.idle += 1;
+        if self.idle >= self.limit {
+            return Err(PrimeError::AttemptsExhausted);
+        }
+        Ok(None)
+    }
+}
+
+/// Primes found on the calling thread, one search slot at a time.
+pub struct PrimeStream {
+    space: Space,
+    candidates: Candidates,
+    seed: [u8; 32],
+    slot: u64,
+    verification: VerificationPolicy,
+    test: ProbableTest,
+    stop: Stop,
+    distinct: Distinct,
+    error: Option<PrimeError>,
+}
+
+impl PrimeStream {
+    pub(crate) fn new(
+        space: Space,
+        candidates: Candidates,
+        seed: [u8; 32],
+        verification: VerificationPolicy,
+        test: ProbableTest,
+        stop: Stop,
+    ) -> Self {
+        PrimeStream {
+            distinct: Distinct::new(&space),
+            space,
+            candidates,
+            seed,
+            slot: 0,
+            verification,
+            test,
+            stop,
+            error: None,
+        }
+    }
+
+    /// The error that ended the stream, if it has ended.
+    pub fn error(&self) -> Option<&PrimeError> {
+        self.error.as_ref()
+    }
+
+    fn next_prime(&mut self) -> Result<BigUint, PrimeError> {
+        loop {
+            self.slot += 1;
+            let rng = &mut slot_rng(self.seed, self.slot);
+            let candidate = self.candidates.next(&self.space, rng, &self.stop)?;
+            let rng = &mut test_rng(self.seed, self.slot);
+            let prime = self.verification.screen(self.test, &candidate)
+                && self.verification.confirm_until(&candidate, rng, &self.stop)?;
+            if let Some(prime) = self.distinct.admit(prime.then_some(candidate))? {
+                return Ok(prime);
+            }
+        }
+    }
+}
+
+impl Iterator for PrimeStream {
+    type Item = BigUint;
+
+    fn next(&mut self) -> Option<BigUint> {
+        if self.error.is_some() {
+            return None;
+        }
+        self.next_prime().map_err(|e| self.error = Some(e)).ok()
+    }
+}
+
+/// Primes found by background workers, yielded in the order [`PrimeStream`]
+/// would yield them. Dropping the stream stops the workers.
+pub struct ParPrimeStream {
+    shared: Arc<Shared>,
+    distinct: Distinct,
+    error: Option<PrimeError>,
+}
+
+struct Shared {
+    slots: Slots,
+    verification: VerificationPolicy,
+    test: ProbableTest,
+    stop: Stop,
+    /// Slots at or after this one are not worth testing: the slot of the
+    /// first error, or 0 once the stream is dropped.
+    closed: Arc<AtomicU64>,
+    /// How many primes the workers may find ahead of the consumer.
+    capacity: usize,
+    results: Mutex<Results>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Results {
+    /// The outcome of each tested slot not yet consumed: `None` for a
+    /// rejected candidate.
+    slots: BTreeMap<u64, Option<Result<BigUint, PrimeError>>>,
+    /// The slot the consumer is waiting on.
+    next: u64,
+    /// Primes among `slots`.
+    ready: usize,
+}
+
+impl ParPrimeStream {
+    pub(crate) fn new(
+        slots: Slots,
+        pool: Arc<rayon::ThreadPool>,
+        verification: VerificationPolicy,
+        test: ProbableTest,
+        stop: Stop,
+    ) -> Self {
+        let distinct = Distinct::new(slots.space());
+        let shared = Arc::new(Shared {
+            slots,
+            verification,
+            test,
+            stop,
+            closed: Arc::new(AtomicU64::new(u64::MAX)),
+            capacity: 2 * pool.current_num_threads(),
+            results: Mutex::new(Results { next: 1, ..Results::default() }),
+            changed: Condvar::new(),
+        });
+        let worker = shared.clone();
+        pool.spawn_broadcast(move |_| worker.work());
+        ParPrimeStream { shared, distinct, error: None }
+    }
+
+    /// The error that ended the stream, if it has ended.
+    pub fn error(&self) -> Option<&PrimeError> {
+        self.error.as_ref()
+    }
+}
+
+impl Shared {
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed) == 0
+    }
+
+    /// Stops the workers, abandoning any checks they are running.
+    fn close(&self) {
+        self.closed.store(0, Ordering::Relaxed);
+        let _results = self.results.lock().unwrap();
+        self.changed.notify_all();
+    }
+
+    /// One worker: claims slots and tests their candidates, parking while
+    /// `capacity` primes wait for the consumer.
+    fn work(&self) {
+        loop {
+            {
+                let mut results = self.results.lock().unwrap();
+                while results.ready >= self.capacity && !self.is_closed() {
+                    results = self.changed.wait(results).unwrap();
+                }
+            }
+            let Some((slot, candidate)) = self.slots.claim(&self.closed, &self.stop) else {
+                return;
+            };
+            let stop = self.stop.for_slot(&self.closed, slot);
+            let rng = &mut test_rng(self.slots.seed(), slot);
+            let result = match candidate {
+                Ok(c) if !self.verification.screen(self.test, &c) => None,
+                Ok(c) => match self.verification.confirm_until(&c, rng, &stop) {
+                    Ok(prime) => prime.then_some(Ok(c)),
+                    // Abandoned because the stream closed.
+                    Err(PrimeError::Cancelled) if self.stop.check().is_ok() => return,
+                    Err(e) => Some(Err(e)),
+                },
+                Err(e) => Some(Err(e)),
+            };
+            if let Some(Err(_)) = result {
+                self.closed.fetch_min(slot, Ordering::Relaxed);
+            }
+            let mut results = self.results.lock().unwrap();
+            results.ready += matches!(result, Some(Ok(_))) as usize;
+            results.slots.insert(slot, result);
+            self.changed.notify_all();
+        }
+    }
+
+    /// Waits for the next slot in order and returns its outcome, `None` for
+    /// a rejected candidate.
+    fn take(&self) -> Result<Option<BigUint>, PrimeError> {
+        let mut results = self.results.lock().unwrap();
+        loop {
+            let next = results.next;
+            match results.slots.remove(&next) {
+                Some(result) => {
+                    results.next += 1;
+                    if let Some(Ok(_)) = result {
+                        results.ready -= 1;
+                        self.changed.notify_all();
+                    }
+                    return result.transpose();
+                }
+                None => results = self.changed.wait(results).unwrap(),
+            }
+        }
+    }
+}
+
+impl Iterator for ParPrimeStream {
+    type Item = BigUint;
+
+    fn next(&mut self) -> Option<BigUint> {
+        while self.error.is_none() {
+            match self.shared.take().and_then(|prime| self.distinct.admit(prime)) {
+                Ok(Some(prime)) => return Some(prime),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.shared.close();
+                    self.error = Some(e);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for ParPrimeStream {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Parallelism, PrimeError, PrimeGenerator, SearchMode, VerificationPolicy};
+
+    fn one_digit() -> PrimeGenerator<rand_chacha::ChaCha20Rng> {
+        PrimeGenerator::new().digits(1).verification(VerificationPolicy::Bpsw).seed(11)
+    }
+
+    #[test]
+    fn stream_ends_once_a_tiny_space_runs_dry() {
+        for mode in [SearchMode::Random, SearchMode::Incremental, SearchMode::Windowed] {
+            let mut stream = one_digit().search_mode(mode).stream().unwrap();
+            let mut primes: Vec<u32> = stream.by_ref().map(|p| p.try_into().unwrap()).collect();
+            primes.sort();
+            assert_eq!(primes, [3, 5, 7]);
+            assert_eq!(stream.error(), Some(&PrimeError::AttemptsExhausted));
+        }
+    }
+
+    #[test]
+    fn par_stream_ends_once_a_tiny_space_runs_dry() {
+        let sequential: Vec<_> = one_digit().stream().unwrap().collect();
+        for threads in [1, 3] {
+            let generator = one_digit().parallelism(Parallelism::Threads(threads));
+            let mut stream = generator.par_stream().unwrap();
+            assert_eq!(stream.by_ref().collect::<Vec<_>>(), sequential);
+            assert_eq!(stream.error(), Some(&PrimeError::AttemptsExhausted));
+        }
+    }
+
+    #[test]
+    fn par_stream_matches_stream() {
+        let generator =
+            || PrimeGenerator::new().bits(96).verification(VerificationPolicy::Bpsw).seed(5);
+        let sequential: Vec<_> = generator().stream().unwrap().take(20).collect();
+        for threads in [1, 2, 4] {
+            let generator = generator().parallelism(Parallelism::Threads(threads));
+            assert_eq!(generator.par_stream().unwrap().take(20).collect::<Vec<_>>(), sequential);
+        }
+    }
+}
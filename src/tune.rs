@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use rand::thread_rng;
+
+use crate::candidate::{Size, random_candidate};
+use crate::error::PrimeError;
+use crate::primality::ProbableTest;
+use crate::sieve::{Sieve, odd_primes_below};
+
+/// Trial-division limits considered by [`tune_trial_division`].
+pub const TUNING_LIMITS: [u32; 13] = [
+    1 << 8, 1 << 9, 1 << 10, 1 << 11, 1 << 12, 1 << 13, 1 << 14, 1 << 15, 1 << 16, 1 << 17,
+    1 << 18, 1 << 19, 1 << 20,
+];
+
+/// Random candidates timed per measurement.
+const SAMPLES: usize = 32;
+
+/// Outcome of [`tune_trial_division`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrialDivisionTuning {
+    /// The limit with the lowest expected cost.
+    pub limit: u32,
+    /// Expected time spent rejecting composites per prime found with that
+    /// limit. The full test of the prime itself comes on top, and does not
+    /// depend on the limit.
+    pub time_per_prime: Duration,
+}
+
+/// Picks the trial-division limit from [`TUNING_LIMITS`] that minimizes the
+/// expected time per probable prime of the given size on this machine,
+/// when survivors of trial division are screened with `test`.
+///
+/// Times the trial division the generator actually runs at each limit
+/// (switching to [`primorial_check`](crate::primorial_check) for long prime
+/// lists) and `test` on random candidates, then models the cost of a
+/// candidate with limit `L` as that trial division plus, if it survives
+/// every prime below `L`, one run of `test`. About `ln(N) / 2` odd
+/// candidates are needed per prime, regardless of `L`.
+///
+/// Fails for sizes that contain no primes.
+pub fn tune_trial_division(
+    size: Size,
+    test: ProbableTest,
+) -> Result<TrialDivisionTuning, PrimeError> {
+    let mut rng = thread_rng();
+    let samples = (0..SAMPLES)
+        .map(|_| random_candidate(&mut rng, size))
+        .collect::<Result<Vec<BigUint>, _>>()?;
+    let time_per_sample = |check: &dyn Fn(&BigUint) -> bool| {
+        let start = Instant::now();
+        for candidate in &samples {
+            std::hint::black_box(check(candidate));
+        }
+        start.elapsed().as_secs_f64() / SAMPLES as f64
+    };
+    let test_time = time_per_sample(&|candidate| test.check(candidate));
+
+    let candidates_per_prime = size.approx_digits() as f64 * std::f64::consts::LN_10 / 2.0;
+    let mut best = (f64::INFINITY, TUNING_LIMITS[0]);
+    let mut reach = 1.0;
+    let primes = odd_primes_below(TUNING_LIMITS[TUNING_LIMITS.len() - 1]);
+    let mut next = primes.iter().peekable();
+    for &limit in &TUNING_LIMITS {
+        while let Some(&&p) = next.peek() {
+            if p >= limit {
+                break;
+            }
+            reach *= 1.0 - 1.0 / p as f64;
+            next.next();
+        }
+        let sieve = Sieve::prime(Some(limit));
+        // Builds the cached prime products outside the timing.
+        sieve.check(&samples[0]);
+        let cost = time_per_sample(&|candidate| sieve.check(candidate)) + reach * test_time;
+        if cost < best.0 {
+            best = (cost, limit);
+        }
+    }
+
+    Ok(TrialDivisionTuning {
+        limit: best.1,
+        time_per_prime: Duration::from_secs_f64(best.0 * candidates_per_prime),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_a_listed_limit() {
+        for test in [ProbableTest::Strict, ProbableTest::Bpsw] {
+            let tuning = tune_trial_division(Size::Bits { bits: 512, top_bits: 1 }, test).unwrap();
+            assert!(TUNING_LIMITS.contains(&tuning.limit));
+            assert!(tuning.time_per_prime > Duration::ZERO);
+        }
+        assert!(tune_trial_division(Size::Digits(0), ProbableTest::Strict).is_err());
+    }
+}
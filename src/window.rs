@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::ToPrimitive;
+use rand::Rng;
+
+use crate::cancel::Stop;
+use crate::constraint::Space;
+use crate::error::PrimeError;
+use crate::sieve::{Sieve, odd_primes_below};
+
+/// Consecutive candidates sieved at once.
+pub const WINDOW_LEN: usize = 1 << 14;
+
+/// Interval sieve over windows of [`WINDOW_LEN`] consecutive points of the
+/// search space. Each window is a bit array; for every prime below the bound
+/// (or the sieve's trial-division limit, if that is higher) the first
+/// multiple is located with one big-number remainder and the rest are
+/// crossed off by striding through the array. Only survivors are handed on
+/// to the probable-prime test, so they pass [`Sieve::check`] as well.
+#[derive(Clone, Debug)]
+pub(crate) struct Window {
+    sieve: Sieve,
+    primes: Arc<Vec<u32>>,
+    /// `step^-1 mod r` for each prime, or `None` if `r` divides the step.
+    inverses: Vec<Option<u32>>,
+    start: BigUint,
+    remaining: u64,
+    survivors: Vec<u64>,
+    window_start: BigUint,
+}
+
+impl Window {
+    pub(crate) fn new<R: Rng + ?Sized>(space: &Space, sieve: Sieve, bound: u32, rng: &mut R) -> Self {
+        let primes = odd_primes_below(bound.max(sieve.limit()));
+        let inverses = primes
+            .iter()
+            .map(|&r| inverse_mod((space.step() % r).to_u32().unwrap(), r))
+            .collect();
+        let mut window = Window {
+            sieve,
+            primes,
+            inverses,
+            start: BigUint::default(),
+            remaining: 0,
+            survivors: Vec::new(),
+            window_start: BigUint::default(),
+        };
+        window.restart(space, rng);
+        window
+    }
+
+    fn restart<R: Rng + ?Sized>(&mut self, space: &Space, rng: &mut R) {
+        let k = rng.gen_biguint_below(space.count());
+        self.start = space.nth(&k);
+        self.remaining = (space.count() - k).to_u64().unwrap_or(u64::MAX);
+    }
+
+    /// Sieves the next window, leaving its survivors (as offsets, in
+    /// descending order) in `self.survivors`.
+    fn fill(&mut self, space: &Space) {
+        let len = WINDOW_LEN.min(self.remaining as usize);
+        let mut composite = vec![0u64; len.div_ceil(64)];
+        let small_start = self.start.to_u64();
+
+        for (&r, inverse) in self.primes.iter().zip(&self.inverses) {
+            let Some(inverse) = *inverse else {
+                continue;
+            };
+            let rem = (&self.start % r).to_u64().unwrap();
+            let (r, inverse) = (r as u64, inverse as u64);
+            let forbidden: &[u64] = if self.sieve.is_safe() { &[0, 1] } else { &[0] };
+            for &target in forbidden {
+                // First offset i with start + i * step = target (mod r).
+                let mut i = ((target + r - rem) % r * inverse % r) as usize;
+                // Never cross off the sieving prime itself, or 2r + 1.
+                if let Some(s) = small_start {
+                    let step = space.step().to_u128().unwrap_or(u128::MAX);
+                    let value = (i as u128).saturating_mul(step).saturating_add(s as u128);
+                    if value == r as u128 || (target == 1 && value == 2 * r as u128 + 1) {
+                        i += r as usize;
+                    }
+                }
+                while i < len {
+                    composite[i / 64] |= 1 << (i % 64);
+                    i += r as usize;
+                }
+            }
+        }
+
+        self.survivors = (0..len as u64)
+            .rev()
+            .filter(|&i| composite[i as usize / 64] & (1 << (i % 64)) == 0)
+            .collect();
+        self.window_start = self.start.clone();
+        self.start += space.step() * len;
+        self.remaining -= len as u64;
+    }
+
+    /// Returns the next survivor that is also outside the excluded residues
+    /// of `space`, checking `stop` before sieving each window and giving up
+    /// after [`Space::attempts`] points.
+    pub(crate) fn next<R: Rng + ?Sized>(
+        &mut self,
+        space: &Space,
+        rng: &mut R,
+        stop: &Stop,
+    ) -> Result<BigUint, PrimeError> {
+        let mut scanned = 0u64;
+        loop {
+            let Some(offset) = self.survivors.pop() else {
+                stop.check()?;
+                if scanned >= space.attempts() {
+                    return Err(PrimeError::AttemptsExhausted);
+                }
+                if self.remaining == 0 {
+                    self.restart(space, rng);
+                }
+                scanned = scanned.saturating_add(WINDOW_LEN.min(self.remaining as usize) as u64);
+                self.fill(space);
+                continue;
+            };
+            let candidate = &self.window_start + space.step() * offset;
+            if space.admits(&candidate) {
+                return Ok(candidate);
+            }
+        }
+    }
+}
+
+/// `a^-1 mod r`, or `None` if `r` divides `a`; `r` is prime.
+fn inverse_mod(a: u32, r: u32) -> Option<u32> {
+    let (mut old_r, mut cur_r) = (a as i64, r as i64);
+    let (mut old_s, mut cur_s) = (1i64, 0i64);
+    while cur_r != 0 {
+        let q = old_r / cur_r;
+        (old_r, cur_r) = (cur_r, old_r - q * cur_r);
+        (old_s, cur_s) = (cur_s, old_s - q * cur_s);
+    }
+    (old_r == 1).then(|| old_s.rem_euclid(r as i64) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::candidate::Size;
+    use crate::constraint::Constraints;
+
+    #[test]
+    fn candidates_satisfy_the_constraints() {
+        let lo = BigUint::from(10u32).pow(30);
+        // Three windows' worth of the first progression, so walks restart.
+        let hi = &lo + 33792u32 * 3 * WINDOW_LEN as u32;
+        let excluded = [(1, 5), (3, 7)];
+        let (prime, safe) = ([(5u32, 12u32), (1, 1024), (7, 11)], [(11u32, 12u32), (7, 11)]);
+        for (residues, narrow, sieve) in [
+            (&prime[..], false, Sieve::prime(None)),
+            (&prime[..], true, Sieve::prime(None)),
+            (&safe[..], false, Sieve::safe(None)),
+        ] {
+            let mut constraints = Constraints::new();
+            for &(r, m) in residues {
+                constraints = constraints.residue(r, m);
+            }
+            for &(r, m) in &excluded {
+                constraints = constraints.exclude(r, m);
+            }
+            if narrow {
+                constraints = constraints.range(lo.clone(), hi.clone());
+            }
+            let space = constraints.resolve(Size::Digits(31)).unwrap();
+            let rng = &mut ChaCha20Rng::seed_from_u64(4);
+            let mut search = Window::new(&space, sieve.clone(), 1 << 16, rng);
+            for _ in 0..3000 {
+                let p = search.next(&space, rng, &Stop::default()).unwrap();
+                assert!(p.bit(0) && sieve.check(&p), "{}", p);
+                assert!(residues.iter().all(|&(r, m)| &p % m == BigUint::from(r)), "{}", p);
+                assert!(excluded.iter().all(|&(r, m)| &p % m != BigUint::from(r)), "{}", p);
+                assert!(!narrow || (lo <= p && p < hi), "{}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn trial_division_limit_above_the_bound_still_applies() {
+        let space = Constraints::new().resolve(Size::Digits(40)).unwrap();
+        let rng = &mut ChaCha20Rng::seed_from_u64(5);
+        for sieve in [Sieve::prime(Some(1 << 17)), Sieve::safe(Some(1 << 17))] {
+            let mut search = Window::new(&space, sieve.clone(), 1 << 8, rng);
+            for _ in 0..500 {
+                let p = search.next(&space, rng, &Stop::default()).unwrap();
+                assert!(sieve.check(&p), "{}", p);
+            }
+        }
+    }
+}